@@ -0,0 +1,342 @@
+use std::fmt;
+
+// The registers of the CPU, arithmetic on them wraps around on overflow
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Registers {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Registers {
+    // Look up a register by name
+    pub fn get(&self, name: &str) -> Option<i32> {
+        match name {
+            "x" => Some(self.x),
+            "y" => Some(self.y),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Registers {
+    fn default() -> Self {
+        Self { x: 1, y: 0 }
+    }
+}
+
+// Where the CPU goes once an instruction has completed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Control {
+    // Continue with the following instruction
+    Next,
+    // Jump by an offset relative to the completed instruction
+    Jump(i32),
+}
+
+// An entry of the opcode table, the effect is applied at the end of the last cycle
+#[derive(Clone, Copy, Debug)]
+pub struct Opcode {
+    pub mnemonic: &'static str,
+    pub cycles: u32,
+    pub takes_operand: bool,
+    pub effect: fn(&mut Registers, i32) -> Control,
+}
+
+impl PartialEq for Opcode {
+    fn eq(&self, other: &Self) -> bool {
+        self.mnemonic == other.mnemonic
+            && self.cycles == other.cycles
+            && self.takes_operand == other.takes_operand
+    }
+}
+
+impl Eq for Opcode {}
+
+pub const NOOP: Opcode = Opcode {
+    mnemonic: "noop",
+    cycles: 1,
+    takes_operand: false,
+    effect: |_, _| Control::Next,
+};
+
+pub const ADDX: Opcode = Opcode {
+    mnemonic: "addx",
+    cycles: 2,
+    takes_operand: true,
+    effect: |registers, value| {
+        registers.x = registers.x.wrapping_add(value);
+        Control::Next
+    },
+};
+
+pub const ADDY: Opcode = Opcode {
+    mnemonic: "addy",
+    cycles: 2,
+    takes_operand: true,
+    effect: |registers, value| {
+        registers.y = registers.y.wrapping_add(value);
+        Control::Next
+    },
+};
+
+pub const MULX: Opcode = Opcode {
+    mnemonic: "mulx",
+    cycles: 3,
+    takes_operand: true,
+    effect: |registers, value| {
+        registers.x = registers.x.wrapping_mul(value);
+        Control::Next
+    },
+};
+
+pub const JMP: Opcode = Opcode {
+    mnemonic: "jmp",
+    cycles: 1,
+    takes_operand: true,
+    effect: |_, offset| Control::Jump(offset),
+};
+
+// A decoded instruction, the operand is 0 for opcodes that don't take one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub operand: i32,
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.opcode.takes_operand {
+            write!(f, "{} {}", self.opcode.mnemonic, self.operand)
+        } else {
+            write!(f, "{}", self.opcode.mnemonic)
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AssembleError {
+    UnknownMnemonic { line: usize, mnemonic: String },
+    MissingOperand { line: usize, mnemonic: String },
+    InvalidOperand { line: usize, operand: String },
+    UnexpectedOperand { line: usize, mnemonic: String },
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssembleError::UnknownMnemonic { line, mnemonic } => {
+                write!(f, "Line {}: unknown instruction {}", line, mnemonic)
+            }
+            AssembleError::MissingOperand { line, mnemonic } => {
+                write!(f, "Line {}: {} expects an operand", line, mnemonic)
+            }
+            AssembleError::InvalidOperand { line, operand } => {
+                write!(f, "Line {}: invalid operand {}", line, operand)
+            }
+            AssembleError::UnexpectedOperand { line, mnemonic } => {
+                write!(f, "Line {}: {} doesn't take an operand", line, mnemonic)
+            }
+        }
+    }
+}
+
+// The opcodes understood by the assembler
+#[derive(Clone, Debug)]
+pub struct InstructionSet {
+    opcodes: Vec<Opcode>,
+}
+
+impl InstructionSet {
+    // The instructions of the puzzle: noop and addx
+    pub fn standard() -> Self {
+        Self {
+            opcodes: vec![NOOP, ADDX],
+        }
+    }
+
+    // The puzzle instructions plus addy, mulx and jmp
+    pub fn extended() -> Self {
+        Self::standard().with(ADDY).with(MULX).with(JMP)
+    }
+
+    // Add an opcode, replacing any opcode with the same mnemonic
+    pub fn with(mut self, opcode: Opcode) -> Self {
+        self.opcodes.retain(|o| o.mnemonic != opcode.mnemonic);
+        self.opcodes.push(opcode);
+        self
+    }
+
+    fn opcode(&self, mnemonic: &str) -> Option<Opcode> {
+        self.opcodes
+            .iter()
+            .find(|o| o.mnemonic == mnemonic)
+            .copied()
+    }
+
+    // Turn the program text into instructions, one instruction per line.
+    // Anything after a `;` is a comment, blank lines are skipped.
+    pub fn assemble(&self, input: &str) -> Result<Vec<Instruction>, AssembleError> {
+        let mut instructions = vec![];
+
+        for (index, text) in input.lines().enumerate() {
+            let line = index + 1;
+            let code = text.split(';').next().unwrap_or_default();
+            let mut parts = code.split_whitespace();
+            let mnemonic = match parts.next() {
+                Some(mnemonic) => mnemonic,
+                None => continue,
+            };
+
+            let opcode = self
+                .opcode(mnemonic)
+                .ok_or_else(|| AssembleError::UnknownMnemonic {
+                    line,
+                    mnemonic: mnemonic.to_string(),
+                })?;
+
+            let operand = match (opcode.takes_operand, parts.next()) {
+                (true, Some(operand)) => {
+                    operand
+                        .parse::<i32>()
+                        .map_err(|_| AssembleError::InvalidOperand {
+                            line,
+                            operand: operand.to_string(),
+                        })?
+                }
+                (true, None) => {
+                    return Err(AssembleError::MissingOperand {
+                        line,
+                        mnemonic: mnemonic.to_string(),
+                    })
+                }
+                (false, None) => 0,
+                (false, Some(_)) => {
+                    return Err(AssembleError::UnexpectedOperand {
+                        line,
+                        mnemonic: mnemonic.to_string(),
+                    })
+                }
+            };
+
+            if let Some(operand) = parts.next() {
+                return Err(AssembleError::InvalidOperand {
+                    line,
+                    operand: operand.to_string(),
+                });
+            }
+
+            instructions.push(Instruction { opcode, operand });
+        }
+
+        Ok(instructions)
+    }
+}
+
+// Define the CPU structure
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug)]
+pub struct CPU {
+    pub registers: Registers,
+    // Index of the next instruction to fetch
+    pub program_counter: usize,
+    // The instruction being executed and the number of cycles left before it completes
+    current_instruction: Option<(Instruction, u32)>,
+}
+
+impl CPU {
+    // Initialize the CPU
+    pub fn new() -> Self {
+        Self {
+            registers: Registers::default(),
+            program_counter: 0,
+            current_instruction: None,
+        }
+    }
+
+    // The instruction being executed, None between instructions
+    pub fn current_instruction(&self) -> Option<Instruction> {
+        self.current_instruction.map(|(instruction, _)| instruction)
+    }
+
+    // Execute the current instruction, an idle CPU simply lets the cycle pass
+    fn execute(&mut self) {
+        if let Some((instruction, cycles)) = self.current_instruction {
+            if cycles > 1 {
+                self.current_instruction = Some((instruction, cycles - 1));
+                return;
+            }
+
+            self.current_instruction = None;
+            match (instruction.opcode.effect)(&mut self.registers, instruction.operand) {
+                Control::Next => self.program_counter += 1,
+                Control::Jump(offset) => {
+                    // Jumping before the first instruction ends the program
+                    self.program_counter = (self.program_counter as i64 + offset as i64)
+                        .try_into()
+                        .unwrap_or(usize::MAX);
+                }
+            }
+        }
+    }
+
+    // Run the program one cycle at a time, yielding a sample for every cycle.
+    // The trace ends when the program counter leaves the program, a program
+    // that jumps backwards can run forever.
+    pub fn trace(self, instructions: &[Instruction]) -> Trace<'_> {
+        Trace {
+            cpu: self,
+            instructions,
+            cycle: 0,
+        }
+    }
+}
+
+// The state of the X register for a single cycle
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CycleSample {
+    pub cycle: u32,
+    // Value of X while the cycle is running
+    pub x_during: i32,
+    // Value of X once the cycle has completed
+    pub x_after: i32,
+}
+
+// Iterator over the cycles of a running program
+pub struct Trace<'a> {
+    cpu: CPU,
+    instructions: &'a [Instruction],
+    cycle: u32,
+}
+
+impl Trace<'_> {
+    pub fn cpu(&self) -> &CPU {
+        &self.cpu
+    }
+
+    // The number of cycles run so far
+    pub fn elapsed_cycles(&self) -> u32 {
+        self.cycle
+    }
+}
+
+impl Iterator for Trace<'_> {
+    type Item = CycleSample;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Fetch the next instruction once the previous one has completed
+        if self.cpu.current_instruction.is_none() {
+            let instruction = *self.instructions.get(self.cpu.program_counter)?;
+            self.cpu.current_instruction = Some((instruction, instruction.opcode.cycles.max(1)));
+        }
+
+        self.cycle += 1;
+        let x_during = self.cpu.registers.x;
+        self.cpu.execute();
+
+        Some(CycleSample {
+            cycle: self.cycle,
+            x_during,
+            x_after: self.cpu.registers.x,
+        })
+    }
+}
@@ -0,0 +1,292 @@
+use std::{
+    collections::BTreeSet,
+    io::{self, BufRead, Write},
+};
+
+use crate::{
+    cpu::{CycleSample, Trace},
+    Crt,
+};
+
+const HELP: &str = "Commands:
+  step [n]       run n cycles, 1 by default (s)
+  continue       run until a breakpoint, a watched register changes or the program ends (c)
+  break <cycle>  stop before the given cycle runs (b)
+  watch <reg>    stop when the register changes (w)
+  registers      print the registers (r)
+  screen         print the screen drawn so far
+  help           print this message (h)
+  quit           leave the debugger (q)";
+
+// Why the CPU stopped running
+enum Stop {
+    Breakpoint(u32),
+    Watch {
+        register: String,
+        from: i32,
+        to: i32,
+    },
+    Finished,
+}
+
+// Step through a program, reading commands from `input` so sessions can be scripted
+pub struct Debugger<'a> {
+    trace: Trace<'a>,
+    crt: Crt,
+    samples: Vec<CycleSample>,
+    breakpoints: BTreeSet<u32>,
+    watches: Vec<String>,
+    finished: bool,
+}
+
+impl<'a> Debugger<'a> {
+    pub fn new(trace: Trace<'a>, crt: Crt) -> Self {
+        Self {
+            trace,
+            crt,
+            samples: vec![],
+            breakpoints: BTreeSet::new(),
+            watches: vec![],
+            finished: false,
+        }
+    }
+
+    // Run a single cycle, returning every reason the CPU should stop,
+    // a watch firing doesn't hide a breakpoint on the following cycle
+    fn step(&mut self) -> Vec<Stop> {
+        let before = self.trace.cpu().registers;
+        let sample = match self.trace.next() {
+            Some(sample) => sample,
+            None => {
+                self.finished = true;
+                return vec![Stop::Finished];
+            }
+        };
+        self.samples.push(sample);
+        let after = self.trace.cpu().registers;
+
+        let mut stops = vec![];
+        for register in self.watches.iter() {
+            let from = before.get(register).unwrap_or_default();
+            let to = after.get(register).unwrap_or_default();
+            if from != to {
+                stops.push(Stop::Watch {
+                    register: register.clone(),
+                    from,
+                    to,
+                });
+            }
+        }
+
+        let next_cycle = sample.cycle + 1;
+        if self.breakpoints.contains(&next_cycle) {
+            stops.push(Stop::Breakpoint(next_cycle));
+        }
+
+        stops
+    }
+
+    fn report(&self, stops: Vec<Stop>, output: &mut impl Write) -> io::Result<()> {
+        for stop in stops {
+            self.report_stop(stop, output)?;
+        }
+        self.print_registers(output)
+    }
+
+    fn report_stop(&self, stop: Stop, output: &mut impl Write) -> io::Result<()> {
+        match stop {
+            Stop::Breakpoint(cycle) => writeln!(output, "Breakpoint before cycle {}", cycle),
+            Stop::Watch { register, from, to } => writeln!(
+                output,
+                "Watch {}: {} -> {} at cycle {}",
+                register,
+                from,
+                to,
+                self.trace.elapsed_cycles()
+            ),
+            Stop::Finished => writeln!(
+                output,
+                "Program finished after {} cycles",
+                self.trace.elapsed_cycles()
+            ),
+        }
+    }
+
+    fn print_registers(&self, output: &mut impl Write) -> io::Result<()> {
+        let cpu = self.trace.cpu();
+        writeln!(
+            output,
+            "Cycle {}, pc = {}, x = {}, y = {}",
+            self.trace.elapsed_cycles(),
+            cpu.program_counter,
+            cpu.registers.x,
+            cpu.registers.y
+        )
+    }
+
+    fn print_screen(&self, output: &mut impl Write) -> io::Result<()> {
+        match self.crt.draw(&self.samples) {
+            Ok(frames) => {
+                if let Some(frame) = frames.last() {
+                    for row in frame.iter() {
+                        writeln!(output, "{}", row.iter().collect::<String>())?;
+                    }
+                }
+                Ok(())
+            }
+            Err(err) => writeln!(output, "CRT error: {}", err),
+        }
+    }
+
+    // Read and run commands until `quit` or the end of the input
+    pub fn run(&mut self, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        write!(output, "(dbg) ")?;
+        output.flush()?;
+
+        for line in input.lines() {
+            let line = line?;
+            let mut parts = line.split_whitespace();
+            let command = parts.next().unwrap_or("");
+            let argument = parts.next();
+
+            match command {
+                "" => {}
+                "s" | "step" => {
+                    let count = match argument {
+                        Some(count) => count.parse::<u32>().ok(),
+                        None => Some(1),
+                    };
+                    match count {
+                        Some(count) => {
+                            let mut stops = vec![];
+                            for _ in 0..count {
+                                stops = self.step();
+                                if !stops.is_empty() {
+                                    break;
+                                }
+                            }
+                            self.report(stops, &mut output)?;
+                        }
+                        None => writeln!(output, "Usage: step [n]")?,
+                    }
+                }
+                "c" | "continue" => {
+                    let stops = if self.finished {
+                        vec![Stop::Finished]
+                    } else {
+                        loop {
+                            let stops = self.step();
+                            if !stops.is_empty() {
+                                break stops;
+                            }
+                        }
+                    };
+                    self.report(stops, &mut output)?;
+                }
+                "b" | "break" => match argument.map(|n| n.parse::<u32>()) {
+                    // Breakpoints are checked between cycles, so the CPU has to get there first
+                    Some(Ok(cycle)) if cycle <= self.trace.elapsed_cycles() + 1 => writeln!(
+                        output,
+                        "Can't break before cycle {}, the next cycle to run is {}",
+                        cycle,
+                        self.trace.elapsed_cycles() + 1
+                    )?,
+                    Some(Ok(cycle)) => {
+                        self.breakpoints.insert(cycle);
+                        writeln!(output, "Breakpoint set before cycle {}", cycle)?;
+                    }
+                    _ => writeln!(output, "Usage: break <cycle>")?,
+                },
+                "w" | "watch" => match argument {
+                    Some(register) if self.trace.cpu().registers.get(register).is_some() => {
+                        self.watches.push(register.to_string());
+                        writeln!(output, "Watching {}", register)?;
+                    }
+                    _ => writeln!(output, "Usage: watch <x|y>")?,
+                },
+                "r" | "registers" => self.print_registers(&mut output)?,
+                "screen" => self.print_screen(&mut output)?,
+                "h" | "help" => writeln!(output, "{}", HELP)?,
+                "q" | "quit" => return Ok(()),
+                _ => writeln!(output, "Unknown command {}, try help", command)?,
+            }
+
+            write!(output, "(dbg) ")?;
+            output.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cpu::{InstructionSet, CPU},
+        FrameOverflow,
+    };
+
+    fn transcript(program: &str, commands: &str) -> String {
+        let instructions = InstructionSet::standard().assemble(program).unwrap();
+        let crt = Crt::new(40, 6, 3, FrameOverflow::NewFrame);
+        let mut output = vec![];
+        Debugger::new(CPU::new().trace(&instructions), crt)
+            .run(commands.as_bytes(), &mut output)
+            .unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn scripted_session() {
+        let output = transcript(
+            "noop\naddx 3\naddx -5\n",
+            "break 1\nbreak 3\nwatch x\ncontinue\ncontinue\nstep\ncontinue\ncontinue\nq\n",
+        );
+
+        assert_eq!(
+            output,
+            "(dbg) Can't break before cycle 1, the next cycle to run is 1
+(dbg) Breakpoint set before cycle 3
+(dbg) Watching x
+(dbg) Breakpoint before cycle 3
+Cycle 2, pc = 1, x = 1, y = 0
+(dbg) Watch x: 1 -> 4 at cycle 3
+Cycle 3, pc = 2, x = 4, y = 0
+(dbg) Cycle 4, pc = 2, x = 4, y = 0
+(dbg) Watch x: 4 -> -1 at cycle 5
+Cycle 5, pc = 3, x = -1, y = 0
+(dbg) Program finished after 5 cycles
+Cycle 5, pc = 3, x = -1, y = 0
+(dbg) "
+        );
+    }
+
+    #[test]
+    fn watch_and_breakpoint_on_the_same_cycle() {
+        let output = transcript(
+            "noop\naddx 3\nnoop\nnoop\nnoop\n",
+            "watch x\nbreak 4\ncontinue\ncontinue\nq\n",
+        );
+
+        assert_eq!(
+            output,
+            "(dbg) Watching x
+(dbg) Breakpoint set before cycle 4
+(dbg) Watch x: 1 -> 4 at cycle 3
+Breakpoint before cycle 4
+Cycle 3, pc = 2, x = 4, y = 0
+(dbg) Program finished after 6 cycles
+Cycle 6, pc = 5, x = 4, y = 0
+(dbg) "
+        );
+    }
+
+    #[test]
+    fn breakpoints_behind_the_cpu_are_rejected() {
+        let output = transcript("noop\nnoop\nnoop\n", "step 2\nbreak 2\nbreak 3\nq\n");
+
+        assert!(output.contains("Can't break before cycle 2, the next cycle to run is 3"));
+        assert!(output.contains("Can't break before cycle 3, the next cycle to run is 3"));
+    }
+}
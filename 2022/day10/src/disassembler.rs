@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+use crate::cpu::{Instruction, Registers, CPU};
+
+// Give up annotating programs that are still running after this many cycles
+const MAX_ANNOTATED_CYCLES: usize = 1_000_000;
+
+// The state of the CPU when an instruction is fetched for the first time
+struct Annotation {
+    cycle: u32,
+    registers: Registers,
+}
+
+// Run the program and record when each instruction starts for the first time
+fn annotations(instructions: &[Instruction]) -> HashMap<usize, Annotation> {
+    let mut annotations = HashMap::new();
+    let mut trace = CPU::new().trace(instructions);
+
+    for _ in 0..MAX_ANNOTATED_CYCLES {
+        let cpu = trace.cpu();
+        if cpu.current_instruction().is_none() {
+            annotations
+                .entry(cpu.program_counter)
+                .or_insert(Annotation {
+                    cycle: trace.elapsed_cycles() + 1,
+                    registers: cpu.registers,
+                });
+        }
+
+        if annotations.len() == instructions.len() || trace.next().is_none() {
+            break;
+        }
+    }
+
+    annotations
+}
+
+// Print the program back in its canonical form, one instruction per line.
+// Annotated lines end with a comment giving the cycle the instruction starts
+// on and the value of X at that point, instructions that never run have none.
+pub fn disassemble(instructions: &[Instruction], annotate: bool) -> String {
+    let annotations = if annotate {
+        annotations(instructions)
+    } else {
+        HashMap::new()
+    };
+
+    let mut output = String::new();
+    for (index, instruction) in instructions.iter().enumerate() {
+        let text = instruction.to_string();
+        match annotations.get(&index) {
+            Some(annotation) => output.push_str(&format!(
+                "{:<10}; cycle {}, x = {}\n",
+                text, annotation.cycle, annotation.registers.x
+            )),
+            None => output.push_str(&format!("{}\n", text)),
+        }
+    }
+
+    output
+}
@@ -0,0 +1,174 @@
+use std::fmt;
+
+use crate::{
+    cpu::{Instruction, Registers, ADDX, NOOP},
+    Crt,
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum GenerateError {
+    // The image doesn't have the dimensions of the screen
+    InvalidSize {
+        width: usize,
+        height: usize,
+    },
+    // A pixel other than `#` or `.`
+    InvalidPixel {
+        row: usize,
+        column: usize,
+        found: char,
+    },
+    // No program draws the image, the given pixel is the first one that can't be reached
+    Unreachable {
+        row: usize,
+        column: usize,
+    },
+}
+
+impl fmt::Display for GenerateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GenerateError::InvalidSize { width, height } => {
+                write!(f, "Image must be {} pixels wide and {} high", width, height)
+            }
+            GenerateError::InvalidPixel { row, column, found } => write!(
+                f,
+                "Invalid pixel {:?} at row {}, column {}",
+                found, row, column
+            ),
+            GenerateError::Unreachable { row, column } => write!(
+                f,
+                "Image can't be drawn, the sprite can't reach row {}, column {}",
+                row, column
+            ),
+        }
+    }
+}
+
+// How the generator got to a state: the previous state and the instruction that was run
+#[derive(Clone, Copy)]
+struct Step {
+    instructions: usize,
+    previous_x: i32,
+    instruction: Instruction,
+}
+
+fn parse_image(image: &str, crt: &Crt) -> Result<Vec<bool>, GenerateError> {
+    let rows: Vec<&str> = image.lines().filter(|line| !line.is_empty()).collect();
+    if rows.len() != crt.height || rows.iter().any(|row| row.chars().count() != crt.width) {
+        return Err(GenerateError::InvalidSize {
+            width: crt.width,
+            height: crt.height,
+        });
+    }
+
+    let mut pixels = vec![];
+    for (row, line) in rows.iter().enumerate() {
+        for (column, pixel) in line.chars().enumerate() {
+            match pixel {
+                '#' => pixels.push(true),
+                '.' => pixels.push(false),
+                found => return Err(GenerateError::InvalidPixel { row, column, found }),
+            }
+        }
+    }
+
+    Ok(pixels)
+}
+
+// Find the shortest program of noop and addx instructions that draws the image
+// on a single frame of the CRT, the program ends exactly on the last pixel.
+pub fn generate(image: &str, crt: &Crt) -> Result<Vec<Instruction>, GenerateError> {
+    let pixels = parse_image(image, crt)?;
+
+    // X values far enough left or right of the screen all draw the same,
+    // so only the ones in between and one dark value on each side matter
+    let min_x = -(crt.sprite_width as i32) - 1;
+    let max_x = (crt.width + crt.sprite_width) as i32;
+    let xs = (max_x - min_x + 1) as usize;
+    let index = |x: i32| (x - min_x) as usize;
+
+    let draws = |pixel: usize, x: i32| crt.sprite_covers(x, pixel % crt.width) == pixels[pixel];
+
+    // steps[pixel][x] is how the program reached the start of `pixel` with X = x
+    let mut steps: Vec<Vec<Option<Step>>> = vec![vec![None; xs]; pixels.len() + 1];
+    let start_x = Registers::default().x;
+    steps[0][index(start_x)] = Some(Step {
+        instructions: 0,
+        previous_x: start_x,
+        instruction: Instruction {
+            opcode: NOOP,
+            operand: 0,
+        },
+    });
+
+    let mut furthest_pixel = 0;
+    for pixel in 0..pixels.len() {
+        for x in min_x..=max_x {
+            let instructions = match steps[pixel][index(x)] {
+                Some(step) => step.instructions + 1,
+                None => continue,
+            };
+            furthest_pixel = pixel;
+
+            let mut visit = |next_pixel: usize, next_x: i32, instruction: Instruction| {
+                let next = &mut steps[next_pixel][index(next_x)];
+                if next.is_none_or(|step| instructions < step.instructions) {
+                    *next = Some(Step {
+                        instructions,
+                        previous_x: x,
+                        instruction,
+                    });
+                }
+            };
+
+            if !draws(pixel, x) {
+                continue;
+            }
+            visit(
+                pixel + 1,
+                x,
+                Instruction {
+                    opcode: NOOP,
+                    operand: 0,
+                },
+            );
+
+            if pixel + 1 < pixels.len() && draws(pixel + 1, x) {
+                for next_x in min_x..=max_x {
+                    let instruction = Instruction {
+                        opcode: ADDX,
+                        operand: next_x - x,
+                    };
+                    visit(pixel + 2, next_x, instruction);
+                }
+            }
+        }
+    }
+
+    // Walk back from the cheapest way to finish the frame
+    let mut x = match (min_x..=max_x)
+        .filter_map(|x| steps[pixels.len()][index(x)].map(|step| (step.instructions, x)))
+        .min()
+    {
+        Some((_, x)) => x,
+        None => {
+            return Err(GenerateError::Unreachable {
+                row: furthest_pixel / crt.width,
+                column: furthest_pixel % crt.width,
+            })
+        }
+    };
+
+    let mut program = vec![];
+    let mut pixel = pixels.len();
+    while pixel > 0 {
+        let step = steps[pixel][index(x)].expect("Every visited state has a step");
+        program.push(step.instruction);
+        pixel -= step.instruction.opcode.cycles as usize;
+        x = step.previous_x;
+    }
+    program.reverse();
+
+    Ok(program)
+}
@@ -1,146 +1,312 @@
-use std::{fs::read_to_string, path::Path};
+mod cpu;
+mod debugger;
+mod disassembler;
+mod generator;
+mod ocr;
 
-// Define the possible instructions
-#[derive(Clone, Copy, Debug)]
-enum Instruction {
-    Noop { cycles: u32 },
-    AddX { value: i32, cycles: u32 },
+use std::{env, fmt, fs::read_to_string, io, path::Path};
+
+use cpu::{CycleSample, InstructionSet, CPU};
+use debugger::Debugger;
+
+// What the CRT does when the program keeps running after the last pixel of a frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FrameOverflow {
+    // Start drawing a new frame from the top left corner
+    NewFrame,
+    // Refuse to draw past the end of the first frame
+    Error,
 }
 
-// Define the CPU structure
-#[allow(clippy::upper_case_acronyms)]
-#[derive(Debug)]
-struct CPU {
-    x: i32,
-    current_instruction: Option<Instruction>,
+#[derive(Debug, PartialEq, Eq)]
+enum CrtError {
+    // The program was still running at this cycle when the frame was full
+    FrameOverflow { cycle: u32 },
+}
+
+impl fmt::Display for CrtError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CrtError::FrameOverflow { cycle } => {
+                write!(
+                    f,
+                    "Program is still running at cycle {} after the frame is full",
+                    cycle
+                )
+            }
+        }
+    }
 }
 
-impl CPU {
-    // Initialize the CPU
-    fn new() -> Self {
+type Frame = Vec<Vec<char>>;
+
+// Frames drawn at most when the CRT starts new ones, a program that loops never ends
+const MAX_FRAMES: usize = 10;
+
+// The CRT screen, the beam draws one pixel per cycle from left to right and top to bottom
+#[derive(Clone, Copy, Debug)]
+struct Crt {
+    width: usize,
+    height: usize,
+    sprite_width: usize,
+    overflow: FrameOverflow,
+}
+
+impl Crt {
+    fn new(width: usize, height: usize, sprite_width: usize, overflow: FrameOverflow) -> Self {
         Self {
-            x: 1,
-            current_instruction: None,
+            width,
+            height,
+            sprite_width,
+            overflow,
         }
     }
 
-    // Execute the current instruction
-    fn execute(&mut self) {
-        if let Some(instruction) = self.current_instruction {
-            match instruction {
-                Instruction::Noop { mut cycles } => {
-                    cycles -= 1;
-                    if cycles == 0 {
-                        self.current_instruction = None;
-                    } else {
-                        self.current_instruction = Some(Instruction::Noop { cycles });
-                    }
-                }
-                Instruction::AddX { mut cycles, value } => {
-                    cycles -= 1;
-                    if cycles == 0 {
-                        self.x += value;
-                        self.current_instruction = None;
-                    } else {
-                        self.current_instruction = Some(Instruction::AddX { cycles, value });
-                    }
-                }
+    // Whether the sprite centered on X covers the given column,
+    // worked out in i64 so X at either end of the i32 range can't overflow
+    fn sprite_covers(&self, x: i32, column: usize) -> bool {
+        let left = x as i64 - (self.sprite_width as i64 - 1) / 2;
+        (left..left + self.sprite_width as i64).contains(&(column as i64))
+    }
+
+    // The number of cycles worth running, one cycle past the first frame
+    // is enough to report an overflow
+    fn cycle_limit(&self) -> usize {
+        let pixels_per_frame = self.width * self.height;
+        match self.overflow {
+            FrameOverflow::NewFrame => pixels_per_frame * MAX_FRAMES,
+            FrameOverflow::Error => pixels_per_frame + 1,
+        }
+    }
+
+    // Draw the frames, the pixel at the beam position is lit when the sprite covers it
+    fn draw(&self, samples: &[CycleSample]) -> Result<Vec<Frame>, CrtError> {
+        let pixels_per_frame = self.width * self.height;
+        let mut frames: Vec<Frame> = vec![];
+
+        for sample in samples.iter() {
+            let laser_beam_position = (sample.cycle - 1) as usize;
+            let frame = laser_beam_position / pixels_per_frame;
+            let row = laser_beam_position % pixels_per_frame / self.width;
+            let column = laser_beam_position % self.width;
+
+            if frame > 0 && self.overflow == FrameOverflow::Error {
+                return Err(CrtError::FrameOverflow {
+                    cycle: sample.cycle,
+                });
             }
-        } else {
-            panic!("No instruction to execute");
+            if frame == frames.len() {
+                frames.push(vec![vec!['.'; self.width]; self.height]);
+            }
+
+            if self.sprite_covers(sample.x_during, column) {
+                frames[frame][row][column] = '#';
+            }
+        }
+
+        Ok(frames)
+    }
+}
+
+// The cycles during which the signal strength is measured
+#[derive(Clone, Debug)]
+enum SamplingSchedule {
+    // An explicit list of cycles
+    Cycles(Vec<u32>),
+    // Every `step` cycles from `start` up to and including `end`
+    Every { start: u32, step: u32, end: u32 },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ScheduleError {
+    // `Every` needs a step of at least one cycle
+    ZeroStep,
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScheduleError::ZeroStep => write!(f, "Sampling step must be at least 1 cycle"),
         }
     }
 }
 
-// Parse instructions from the input file
-fn parse_instructions(input: &str) -> Vec<Instruction> {
-    input
-        .lines()
-        .map(|line| {
-            let mut parts = line.split_whitespace();
-            let instruction = parts.next().unwrap();
-
-            if instruction == "addx" {
-                let value = parts.next().unwrap().parse::<i32>().unwrap();
-                Instruction::AddX { value, cycles: 2 }
-            } else if instruction == "noop" {
-                Instruction::Noop { cycles: 1 }
-            } else {
-                panic!("Unknown instruction: {}", instruction);
+impl SamplingSchedule {
+    // The last cycle sampled, None when nothing is sampled
+    fn last_cycle(&self) -> Option<u32> {
+        self.cycles().ok()?.into_iter().max()
+    }
+
+    fn cycles(&self) -> Result<Vec<u32>, ScheduleError> {
+        match self {
+            SamplingSchedule::Cycles(cycles) => Ok(cycles.clone()),
+            SamplingSchedule::Every { step: 0, .. } => Err(ScheduleError::ZeroStep),
+            SamplingSchedule::Every { start, step, end } => {
+                Ok((*start..=*end).step_by(*step as usize).collect())
             }
+        }
+    }
+}
+
+// The signal strength measured during a single cycle
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct SignalSample {
+    cycle: u32,
+    x: i32,
+    // Wide enough for any cycle number times any value of X
+    strength: i64,
+}
+
+// Multiply the cycle number by the value of X during each scheduled cycle,
+// cycles past the end of the program are skipped
+fn signal_strengths(
+    samples: &[CycleSample],
+    schedule: &SamplingSchedule,
+) -> Result<Vec<SignalSample>, ScheduleError> {
+    let signals = schedule
+        .cycles()?
+        .into_iter()
+        .filter_map(|cycle| {
+            let sample = samples.iter().find(|s| s.cycle == cycle)?;
+            Some(SignalSample {
+                cycle,
+                x: sample.x_during,
+                strength: cycle as i64 * sample.x_during as i64,
+            })
         })
-        .collect()
+        .collect();
+
+    Ok(signals)
 }
 
 // Run the CPU simulation
-fn simulate_cpu(path: &Path) {
+fn simulate_cpu(path: &Path, schedule: &SamplingSchedule, crt: &Crt) {
     println!("----------------------------------");
     println!("Simulating CPU with instructions from {}", path.display());
 
     // Read input from the file and parse the instructions
     let input = read_to_string(path).unwrap();
-    let instructions = parse_instructions(&input);
-
-    // Initialize the CPU
-    let mut cpu = CPU::new();
-    let mut global_cycle_counter = 0;
-    let mut signal_strengths = [20, 60, 100, 140, 180, 220];
-    let mut signal_index = 0;
-
-    // Initialize the CRT screen
-    let mut screen = vec![vec!['.'; 40]; 6];
-    let mut laser_beam_row_position = 0;
-    let mut laser_beam_position = 0;
-
-    // Execute each instruction in the list
-    for instruction in instructions.iter() {
-        // Set the current instruction in the CPU
-        cpu.current_instruction = Some(*instruction);
-
-        // Execute the current instruction
-        while cpu.current_instruction.is_some() {
-            // Increment the global cycle counter
-            global_cycle_counter += 1;
-
-            // If laser_beam_position is in range of x -+1 then draw a '#' on the screen at the current laser_beam_row_position
-            // if not then draw a '.' on the screen at the current laser_beam_row_position which is already done by default
-            if (cpu.x.saturating_sub(1)..=cpu.x.saturating_add(1)).contains(&laser_beam_position) {
-                screen[laser_beam_row_position][laser_beam_position as usize] = '#';
-            }
+    let instructions = match InstructionSet::extended().assemble(&input) {
+        Ok(instructions) => instructions,
+        Err(err) => {
+            println!("Error parsing program: {}", err);
+            return;
+        }
+    };
 
-            // Move the laser beam
-            laser_beam_position += 1;
-            if laser_beam_position == 40 {
-                laser_beam_position = 0;
-                laser_beam_row_position += 1;
-            }
+    // Run the program for as long as the CRT or the sampling schedule needs,
+    // a program that loops never ends on its own
+    let cycle_limit = crt
+        .cycle_limit()
+        .max(schedule.last_cycle().unwrap_or_default() as usize);
+    let mut trace = CPU::new().trace(&instructions);
+    let samples: Vec<CycleSample> = trace.by_ref().take(cycle_limit).collect();
+    if trace.next().is_some() {
+        println!("Program stopped after {} cycles", cycle_limit);
+    }
 
-            // Check if a signal needs to be modified
-            if signal_index < signal_strengths.len()
-                && global_cycle_counter == signal_strengths[signal_index]
-            {
-                println!("x = {}", cpu.x);
-                signal_strengths[signal_index] *= cpu.x;
-                signal_index += 1;
+    // Print the CRT screen content
+    let crt_samples = &samples[..samples.len().min(crt.cycle_limit())];
+    match crt.draw(crt_samples) {
+        Ok(frames) => {
+            for frame in frames.iter() {
+                for row in frame.iter() {
+                    println!("{}", row.iter().collect::<String>());
+                }
+
+                // Read the letters drawn on the frame
+                match ocr::recognize(frame) {
+                    Ok(letters) => println!("Letters: {}", letters),
+                    Err(err) => println!("OCR error: {}", err),
+                }
             }
+        }
+        Err(err) => println!("CRT error: {}", err),
+    }
 
-            // Execute the current instruction
-            cpu.execute();
+    // Calculate and display the sum of signal strengths
+    let signals = match signal_strengths(&samples, schedule) {
+        Ok(signals) => signals,
+        Err(err) => {
+            println!("Error sampling the signal: {}", err);
+            return;
         }
+    };
+    for signal in signals.iter() {
+        println!(
+            "Cycle = {}, x = {}, strength = {}",
+            signal.cycle, signal.x, signal.strength
+        );
     }
+    // Summed as i128 so a long schedule of large strengths can't overflow
+    let sum: i128 = signals.iter().map(|signal| signal.strength as i128).sum();
+    println!("Sum of signal strengths: {}", sum);
+}
 
-    // Print the CRT screen content
-    for row in screen.iter() {
-        println!("{}", row.iter().collect::<String>());
+// Step through a program interactively, commands are read from stdin
+fn debug_cpu(path: &Path) {
+    let input = read_to_string(path).unwrap();
+    let instructions = match InstructionSet::extended().assemble(&input) {
+        Ok(instructions) => instructions,
+        Err(err) => {
+            println!("Error parsing program: {}", err);
+            return;
+        }
+    };
+
+    let crt = Crt::new(40, 6, 3, FrameOverflow::NewFrame);
+    let mut debugger = Debugger::new(CPU::new().trace(&instructions), crt);
+    debugger
+        .run(io::stdin().lock(), io::stdout())
+        .expect("Failed to run the debugger");
+}
+
+// Print a program back in its canonical form
+fn disassemble_file(path: &Path, annotate: bool) {
+    let input = read_to_string(path).unwrap();
+    match InstructionSet::extended().assemble(&input) {
+        Ok(instructions) => print!("{}", disassembler::disassemble(&instructions, annotate)),
+        Err(err) => println!("Error parsing program: {}", err),
     }
+}
 
-    // Calculate and display the sum of signal strengths
-    let sum: i32 = signal_strengths.iter().sum();
-    println!("Signals = {:?}", signal_strengths);
-    println!("Sum of signal strengths: {}", sum);
+// Print a program that draws the `#`/`.` image in the file
+fn generate_program(path: &Path) {
+    let image = read_to_string(path).unwrap();
+    let crt = Crt::new(40, 6, 3, FrameOverflow::Error);
+    match generator::generate(&image, &crt) {
+        Ok(instructions) => print!("{}", disassembler::disassemble(&instructions, false)),
+        Err(err) => println!("Error generating program: {}", err),
+    }
 }
 
 fn main() {
-    simulate_cpu(Path::new("test.txt"));
-    simulate_cpu(Path::new("input.txt"));
+    // `cargo run -- debug <file>` starts the debugger,
+    // `cargo run -- disasm <file> [--annotate]` prints the program back and
+    // `cargo run -- generate <image>` prints a program drawing the image
+    // instead of running the puzzle
+    let args: Vec<String> = env::args().collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        [_, "debug", path] => return debug_cpu(Path::new(path)),
+        [_, "disasm", path] => return disassemble_file(Path::new(path), false),
+        [_, "disasm", path, "--annotate"] => return disassemble_file(Path::new(path), true),
+        [_, "generate", path] => return generate_program(Path::new(path)),
+        _ => {}
+    }
+
+    simulate_cpu(
+        Path::new("test.txt"),
+        &SamplingSchedule::Cycles(vec![20, 60, 100, 140, 180, 220]),
+        &Crt::new(40, 6, 3, FrameOverflow::NewFrame),
+    );
+    simulate_cpu(
+        Path::new("input.txt"),
+        &SamplingSchedule::Every {
+            start: 20,
+            step: 40,
+            end: 220,
+        },
+        &Crt::new(40, 6, 3, FrameOverflow::Error),
+    );
 }
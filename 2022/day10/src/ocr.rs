@@ -0,0 +1,130 @@
+use std::fmt;
+
+const GLYPH_WIDTH: usize = 4;
+const GLYPH_HEIGHT: usize = 6;
+// Letters are separated by a single blank column
+const CELL_WIDTH: usize = GLYPH_WIDTH + 1;
+
+// The capital letters of the Advent of Code font, one string per row.
+// Y is five pixels wide, its last column falls on the separator and isn't compared.
+const FONT: [(char, [&str; GLYPH_HEIGHT]); 18] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Y', ["#...", "#...", ".#.#", "..#.", "..#.", "..#."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+// A glyph that doesn't match any letter of the font
+#[derive(Debug, PartialEq, Eq)]
+pub struct UnknownGlyph {
+    // Position of the glyph in the line of text, starting at 0
+    pub position: usize,
+    pub rows: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum OcrError {
+    // The screen must be exactly one glyph high
+    InvalidHeight(usize),
+    UnknownGlyphs(Vec<UnknownGlyph>),
+}
+
+impl fmt::Display for OcrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OcrError::InvalidHeight(height) => write!(
+                f,
+                "Screen is {} rows high, expected {}",
+                height, GLYPH_HEIGHT
+            ),
+            OcrError::UnknownGlyphs(glyphs) => {
+                write!(f, "{} unrecognised glyph(s)", glyphs.len())?;
+                for glyph in glyphs.iter() {
+                    write!(f, "\nGlyph {}:", glyph.position)?;
+                    for row in glyph.rows.iter() {
+                        write!(f, "\n{}", row)?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// Read the capital letters drawn on the screen, `#` being a lit pixel
+pub fn recognize(screen: &[Vec<char>]) -> Result<String, OcrError> {
+    if screen.len() != GLYPH_HEIGHT {
+        return Err(OcrError::InvalidHeight(screen.len()));
+    }
+
+    let width = screen.iter().map(|row| row.len()).max().unwrap_or(0);
+    let mut letters = String::new();
+    let mut unknown_glyphs = vec![];
+
+    for (position, left) in (0..width).step_by(CELL_WIDTH).enumerate() {
+        // Pad the last glyph with dark pixels if the screen is cut short
+        let rows: Vec<String> = screen
+            .iter()
+            .map(|row| {
+                (left..left + GLYPH_WIDTH)
+                    .map(|column| match row.get(column) {
+                        Some('#') => '#',
+                        _ => '.',
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let letter = FONT
+            .iter()
+            .find(|(_, glyph)| glyph.iter().zip(rows.iter()).all(|(a, b)| a == b))
+            .map(|(letter, _)| *letter);
+
+        match letter {
+            Some(letter) => letters.push(letter),
+            None => unknown_glyphs.push(UnknownGlyph { position, rows }),
+        }
+    }
+
+    if unknown_glyphs.is_empty() {
+        Ok(letters)
+    } else {
+        Err(OcrError::UnknownGlyphs(unknown_glyphs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_i_and_y() {
+        let screen: Vec<Vec<char>> = [
+            ".###.#...#",
+            "..#..#...#",
+            "..#...#.#.",
+            "..#....#..",
+            "..#....#..",
+            ".###...#..",
+        ]
+        .iter()
+        .map(|row| row.chars().collect())
+        .collect();
+
+        assert_eq!(recognize(&screen), Ok("IY".to_string()));
+    }
+}
@@ -0,0 +1,156 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{worry::Worry, Monkey, Operation, Operator, WorryOverflow};
+
+#[derive(Debug)]
+pub enum ExtrapolationError {
+    // Reducing worry levels modulo the divisors only works for `+` and `*`
+    NonModularOperation { monkey: usize },
+    // The product of the divisors doesn't fit in a u64
+    ModulusOverflow,
+    Overflow(WorryOverflow),
+}
+
+impl fmt::Display for ExtrapolationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExtrapolationError::NonModularOperation { monkey } => write!(
+                f,
+                "Monkey {} uses an operation that can't be reduced modulo the divisors",
+                monkey
+            ),
+            ExtrapolationError::ModulusOverflow => {
+                write!(
+                    f,
+                    "The product of the divisors is too large to fit in 64 bits"
+                )
+            }
+            ExtrapolationError::Overflow(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Operation {
+    // Whether the operation commutes with taking the remainder of the worry level
+    fn is_modular(&self) -> bool {
+        match self {
+            Operation::Old | Operation::Value(_) => true,
+            Operation::Binary(left, operator, right) => {
+                matches!(operator, Operator::Add | Operator::Multiply)
+                    && left.is_modular()
+                    && right.is_modular()
+            }
+        }
+    }
+}
+
+// Follow a single item through one round. Returns the monkey holding it and its worry
+// level at the end of the round, and the monkeys that inspected it along the way.
+fn follow_item(
+    monkeys: &[Monkey],
+    modulus: u64,
+    round: u64,
+    mut monkey: usize,
+    mut worry: u64,
+) -> Result<(usize, u64, Vec<usize>), WorryOverflow> {
+    let mut inspected_by = vec![];
+
+    // Items thrown to a monkey further down the line are inspected again this round
+    loop {
+        inspected_by.push(monkey);
+
+        let operation = &monkeys[monkey].operation;
+        worry = operation.apply(&worry).ok_or_else(|| WorryOverflow {
+            round: round as usize,
+            monkey,
+            old: worry.to_string(),
+            operation: operation.clone(),
+        })? % modulus;
+
+        let receiver = if worry.is_divisible_by(monkeys[monkey].divisor) {
+            monkeys[monkey].throw_to.0
+        } else {
+            monkeys[monkey].throw_to.1
+        };
+
+        let passed = receiver <= monkey;
+        monkey = receiver;
+        if passed {
+            return Ok((monkey, worry, inspected_by));
+        }
+    }
+}
+
+// Count the inspections of a single item over the given number of rounds.
+// The item's state at the start of a round is the monkey holding it and its worry
+// level modulo the divisors, once a state repeats the rounds in between repeat forever.
+fn item_inspections(
+    monkeys: &[Monkey],
+    modulus: u64,
+    rounds: u64,
+    monkey: usize,
+    worry: u64,
+) -> Result<Vec<u64>, WorryOverflow> {
+    let mut inspections = vec![0; monkeys.len()];
+    let mut history: Vec<Vec<usize>> = vec![];
+    let mut seen: HashMap<(usize, u64), u64> = HashMap::new();
+    let mut state = (monkey, worry % modulus);
+
+    for round in 0..rounds {
+        if let Some(&cycle_start) = seen.get(&state) {
+            let cycle_length = round - cycle_start;
+            let remaining = rounds - round;
+
+            // Every full lap of the cycle, then the rounds left over
+            for (offset, inspected_by) in history[cycle_start as usize..].iter().enumerate() {
+                let laps = remaining / cycle_length
+                    + u64::from((offset as u64) < remaining % cycle_length);
+                for &monkey in inspected_by {
+                    inspections[monkey] += laps;
+                }
+            }
+            break;
+        }
+        seen.insert(state, round);
+
+        let (monkey, worry, inspected_by) =
+            follow_item(monkeys, modulus, round + 1, state.0, state.1)?;
+        for &monkey in inspected_by.iter() {
+            inspections[monkey] += 1;
+        }
+        history.push(inspected_by);
+        state = (monkey, worry);
+    }
+
+    Ok(inspections)
+}
+
+// The number of items each monkey inspects over any number of rounds, when worry levels
+// are kept in check by taking their remainder by the product of the divisors
+pub fn extrapolate_inspections(
+    monkeys: &[Monkey],
+    rounds: u64,
+) -> Result<Vec<u64>, ExtrapolationError> {
+    if let Some(monkey) = monkeys.iter().position(|m| !m.operation.is_modular()) {
+        return Err(ExtrapolationError::NonModularOperation { monkey });
+    }
+
+    let modulus = monkeys
+        .iter()
+        .try_fold(1u64, |modulus, monkey| modulus.checked_mul(monkey.divisor))
+        .ok_or(ExtrapolationError::ModulusOverflow)?;
+    let mut inspections = vec![0; monkeys.len()];
+
+    for (monkey_idx, monkey) in monkeys.iter().enumerate() {
+        for item in monkey.items.iter() {
+            let item_inspections =
+                item_inspections(monkeys, modulus, rounds, monkey_idx, item.worry)
+                    .map_err(ExtrapolationError::Overflow)?;
+            for (total, count) in inspections.iter_mut().zip(item_inspections) {
+                *total += count;
+            }
+        }
+    }
+
+    Ok(inspections)
+}
@@ -0,0 +1,29 @@
+use std::collections::BTreeMap;
+
+use crate::Monkey;
+
+// How many times each monkey threw an item to each other monkey, keyed by (from, to)
+pub fn throw_graph<W>(monkeys: &[Monkey<W>]) -> BTreeMap<(usize, usize), u64> {
+    let mut graph = BTreeMap::new();
+
+    for item in monkeys.iter().flat_map(|monkey| monkey.items.iter()) {
+        for throw in item.held_by.windows(2) {
+            *graph.entry((throw[0], throw[1])).or_insert(0) += 1;
+        }
+    }
+
+    graph
+}
+
+// Render the throw graph in Graphviz DOT format, edges are labelled with their count
+pub fn to_dot(graph: &BTreeMap<(usize, usize), u64>) -> String {
+    let mut dot = String::from("digraph throws {\n");
+    for ((from, to), count) in graph.iter() {
+        dot.push_str(&format!(
+            "    \"Monkey {}\" -> \"Monkey {}\" [label=\"{}\"];\n",
+            from, to, count
+        ));
+    }
+    dot.push_str("}\n");
+    dot
+}
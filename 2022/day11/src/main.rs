@@ -1,35 +1,168 @@
-use std::fs;
+mod cycles;
+mod graph;
+mod report;
+mod validation;
+mod worry;
+
+use std::{env, fmt, fs};
 
 use nom::{
+    branch::alt,
     bytes::complete::tag,
     character::complete as cc,
-    character::complete::{line_ending, multispace0, space1},
-    combinator::opt,
-    multi::separated_list1,
-    sequence::preceded,
+    character::complete::{line_ending, multispace0, one_of, space0},
+    combinator::map,
+    multi::{many0, separated_list1},
+    sequence::{delimited, pair, preceded},
     IResult,
 };
 
+use report::{Report, RoundStats};
+use validation::ParseError;
+use worry::Worry;
+
 #[derive(Debug, Clone)]
-pub struct Monkey {
+pub struct Monkey<W = u64> {
     pub index: u64,
-    pub items: Vec<u64>,
+    pub items: Vec<Item<W>>,
     pub operation: Operation,
     pub divisor: u64,
     pub throw_to: (usize, usize), // first usize for true, second for false
     pub items_inspected: u64,
 }
 
-#[derive(Clone, Debug)]
-pub enum Operation {
-    Add(Term),
-    Multiply(Term),
+// An item and the monkeys that held it, in order, starting with the one holding it initially
+#[derive(Debug, Clone)]
+pub struct Item<W = u64> {
+    pub id: usize,
+    pub worry: W,
+    pub held_by: Vec<usize>,
 }
 
-#[derive(Clone, Debug)]
-pub enum Term {
+// The right hand side of `new = ...`, an arithmetic expression over `old`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Operation {
     Old,
     Value(u64),
+    Binary(Box<Operation>, Operator, Box<Operation>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+}
+
+impl Monkey {
+    // Switch the items to another representation of worry levels
+    pub fn with_worry<W: Worry>(self) -> Monkey<W> {
+        Monkey {
+            index: self.index,
+            items: self
+                .items
+                .into_iter()
+                .map(|item| Item {
+                    id: item.id,
+                    worry: W::from_u64(item.worry),
+                    held_by: item.held_by,
+                })
+                .collect(),
+            operation: self.operation,
+            divisor: self.divisor,
+            throw_to: self.throw_to,
+            items_inspected: self.items_inspected,
+        }
+    }
+}
+
+impl Operator {
+    fn precedence(&self) -> u8 {
+        match self {
+            Operator::Add | Operator::Subtract => 1,
+            Operator::Multiply | Operator::Divide | Operator::Remainder => 2,
+        }
+    }
+
+    fn symbol(&self) -> char {
+        match self {
+            Operator::Add => '+',
+            Operator::Subtract => '-',
+            Operator::Multiply => '*',
+            Operator::Divide => '/',
+            Operator::Remainder => '%',
+        }
+    }
+}
+
+impl Operation {
+    // Compute the new worry level, None if it overflows, goes below zero or divides by zero
+    pub fn apply<W: Worry>(&self, old: &W) -> Option<W> {
+        match self {
+            Operation::Old => Some(old.clone()),
+            Operation::Value(val) => Some(W::from_u64(*val)),
+            Operation::Binary(left, operator, right) => {
+                let left = left.apply(old)?;
+                let right = right.apply(old)?;
+                match operator {
+                    Operator::Add => left.checked_add(&right),
+                    Operator::Subtract => left.checked_sub(&right),
+                    Operator::Multiply => left.checked_mul(&right),
+                    Operator::Divide => left.checked_div(&right),
+                    Operator::Remainder => left.checked_rem(&right),
+                }
+            }
+        }
+    }
+
+    // Write an operand of `operator`, adding parentheses only where they're needed
+    fn fmt_operand(&self, f: &mut fmt::Formatter, operator: Operator, right: bool) -> fmt::Result {
+        match self {
+            Operation::Binary(_, inner, _)
+                if inner.precedence() < operator.precedence()
+                    || (right && inner.precedence() == operator.precedence()) =>
+            {
+                write!(f, "({})", self)
+            }
+            _ => write!(f, "{}", self),
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operation::Old => write!(f, "old"),
+            Operation::Value(val) => write!(f, "{}", val),
+            Operation::Binary(left, operator, right) => {
+                left.fmt_operand(f, *operator, false)?;
+                write!(f, " {} ", operator.symbol())?;
+                right.fmt_operand(f, *operator, true)
+            }
+        }
+    }
+}
+
+// A worry level couldn't be computed: it grew too large for its representation,
+// went below zero or was divided by zero
+#[derive(Debug)]
+pub struct WorryOverflow {
+    pub round: usize,
+    pub monkey: usize,
+    pub old: String,
+    pub operation: Operation,
+}
+
+impl fmt::Display for WorryOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid worry level in round {}: monkey {} computing new = {} with old = {}",
+            self.round, self.monkey, self.operation, self.old
+        )
+    }
 }
 
 fn u64_parser(input: &str) -> IResult<&str, u64> {
@@ -43,37 +176,53 @@ fn u64_parser(input: &str) -> IResult<&str, u64> {
     }
 }
 
+// Fold `operand (operator operand)*` into a left associative expression
+fn binary_parser<'a>(
+    input: &'a str,
+    operand: fn(&'a str) -> IResult<&'a str, Operation>,
+    operators: &'static str,
+) -> IResult<&'a str, Operation> {
+    let (input, first) = operand(input)?;
+    let (input, rest) = many0(pair(delimited(space0, one_of(operators), space0), operand))(input)?;
+
+    let expression = rest.into_iter().fold(first, |left, (symbol, right)| {
+        let operator = match symbol {
+            '+' => Operator::Add,
+            '-' => Operator::Subtract,
+            '*' => Operator::Multiply,
+            '/' => Operator::Divide,
+            _ => Operator::Remainder,
+        };
+        Operation::Binary(Box::new(left), operator, Box::new(right))
+    });
+    Ok((input, expression))
+}
+
+// old, a number or a parenthesised expression
+fn factor_parser(input: &str) -> IResult<&str, Operation> {
+    alt((
+        map(tag("old"), |_| Operation::Old),
+        map(u64_parser, Operation::Value),
+        delimited(
+            pair(cc::char('('), space0),
+            expression_parser,
+            pair(space0, cc::char(')')),
+        ),
+    ))(input)
+}
+
+fn term_parser(input: &str) -> IResult<&str, Operation> {
+    binary_parser(input, factor_parser, "*/%")
+}
+
+fn expression_parser(input: &str) -> IResult<&str, Operation> {
+    binary_parser(input, term_parser, "+-")
+}
+
 fn operation_parser(input: &str) -> IResult<&str, Operation> {
     let (input, _) = preceded(multispace0, tag("Operation: "))(input)?;
-    let (input, _) = tag("new = old ")(input)?;
-    let (input, operation_str) = nom::branch::alt((tag("+"), tag("*")))(input)?;
-    let (input, _) = space1(input)?;
-    match operation_str {
-        "+" => {
-            let (input, value) = opt(u64_parser)(input)?;
-            match value {
-                Some(val) => Ok((input, Operation::Add(Term::Value(val)))),
-                None => {
-                    let (input, _) = tag("old")(input)?;
-                    Ok((input, Operation::Add(Term::Old)))
-                }
-            }
-        }
-        "*" => {
-            let (input, value) = opt(u64_parser)(input)?;
-            match value {
-                Some(val) => Ok((input, Operation::Multiply(Term::Value(val)))),
-                None => {
-                    let (input, _) = tag("old")(input)?;
-                    Ok((input, Operation::Multiply(Term::Old)))
-                }
-            }
-        }
-        _ => Err(nom::Err::Failure(nom::error::make_error(
-            input,
-            nom::error::ErrorKind::Char,
-        ))),
-    }
+    let (input, _) = tag("new = ")(input)?;
+    expression_parser(input)
 }
 
 fn items_parser(input: &str) -> IResult<&str, Vec<u64>> {
@@ -108,6 +257,16 @@ fn monkey_parser(input: &str) -> IResult<&str, Monkey> {
     let (input, _) = line_ending(input)?;
     let (input, receivers) = receiver_parser(input)?;
 
+    // Items are numbered once every monkey has been parsed
+    let items = items
+        .into_iter()
+        .map(|worry| Item {
+            id: 0,
+            worry,
+            held_by: vec![],
+        })
+        .collect();
+
     Ok((
         input,
         Monkey {
@@ -121,65 +280,127 @@ fn monkey_parser(input: &str) -> IResult<&str, Monkey> {
     ))
 }
 
-fn monkeys_parser(input: &str) -> IResult<&str, Vec<Monkey>> {
-    let (input, monkeys) = nom::multi::separated_list0(multispace0, monkey_parser)(input)?;
-    Ok((input, monkeys))
+// The line of `input` where `rest` starts, counting from 1
+fn line_number(input: &str, rest: &str) -> usize {
+    input[..input.len() - rest.len()].matches('\n').count() + 1
 }
 
-fn main() {
-    let contents = fs::read_to_string("input.txt").expect("Something went wrong reading the file");
+// Parse every monkey in the notes, failing on anything that isn't a monkey
+fn parse_monkeys(input: &str) -> Result<Vec<Monkey>, ParseError> {
+    let mut monkeys = vec![];
+    let mut lines = vec![];
+    let mut rest = input.trim_start();
+
+    while !rest.is_empty() {
+        match monkey_parser(rest) {
+            Ok((remaining, monkey)) => {
+                lines.push(line_number(input, rest));
+                monkeys.push(monkey);
+                rest = remaining.trim_start();
+            }
+            Err(nom::Err::Error(err)) | Err(nom::Err::Failure(err)) => {
+                return Err(ParseError::Syntax {
+                    line: line_number(input, err.input),
+                    text: err.input.lines().next().unwrap_or_default().to_string(),
+                })
+            }
+            Err(nom::Err::Incomplete(_)) => {
+                return Err(ParseError::Syntax {
+                    line: line_number(input, rest),
+                    text: rest.lines().next().unwrap_or_default().to_string(),
+                })
+            }
+        }
+    }
+
+    validation::validate(&monkeys, &lines)?;
 
-    let (_, mut monkeys) =
-        monkeys_parser(&contents).unwrap_or_else(|err| panic!("Error parsing file: {:?}", err));
+    let mut id = 0;
+    for (monkey_idx, monkey) in monkeys.iter_mut().enumerate() {
+        for item in monkey.items.iter_mut() {
+            item.id = id;
+            item.held_by.push(monkey_idx);
+            id += 1;
+        }
+    }
+
+    Ok(monkeys)
+}
+
+// Every monkey inspects and throws all of its items once, in order
+fn play_round<W: Worry>(
+    monkeys: &mut [Monkey<W>],
+    round: usize,
+) -> Result<RoundStats<W>, WorryOverflow> {
+    let mut stats = RoundStats::new(round, monkeys.len());
+
+    for monkey_idx in 0..monkeys.len() {
+        monkeys[monkey_idx].items = monkeys[monkey_idx].items.iter().rev().cloned().collect();
+
+        while let Some(mut item) = monkeys[monkey_idx].items.pop() {
+            monkeys[monkey_idx].items_inspected += 1;
+
+            let worry_level = monkeys[monkey_idx]
+                .operation
+                .apply(&item.worry)
+                .ok_or_else(|| WorryOverflow {
+                    round,
+                    monkey: monkey_idx,
+                    old: item.worry.to_string(),
+                    operation: monkeys[monkey_idx].operation.clone(),
+                })?;
+            stats.record_inspection(monkey_idx, &worry_level);
+
+            // bored worry level is the worry level divided by 3 and rounded down
+            let bored_worry_level = worry_level.div_u64(3);
+
+            let receiver_idx = if bored_worry_level.is_divisible_by(monkeys[monkey_idx].divisor) {
+                monkeys[monkey_idx].throw_to.0
+            } else {
+                monkeys[monkey_idx].throw_to.1
+            };
+
+            println!(
+                "Monkey: {}, Item: {}, Worry: {}, Bored: {}, Receiver: {}",
+                monkey_idx, item.worry, worry_level, bored_worry_level, receiver_idx
+            );
+
+            item.worry = bored_worry_level;
+            item.held_by.push(receiver_idx);
+            monkeys[receiver_idx].items.push(item);
+        }
+    }
+
+    for (held, monkey) in stats.items_held.iter_mut().zip(monkeys.iter()) {
+        *held = monkey.items.len();
+    }
+
+    Ok(stats)
+}
+
+fn simulate<W: Worry>(
+    mut monkeys: Vec<Monkey<W>>,
+    top: usize,
+) -> Result<(Vec<Monkey<W>>, Report<W>), WorryOverflow> {
+    let mut report = Report::new();
 
     for round in 0..20 {
         println!();
         println!("------------ Round {} -------------", round + 1);
 
-        for monkey_idx in 0..monkeys.len() {
-            monkeys[monkey_idx].items = monkeys[monkey_idx].items.iter().rev().cloned().collect();
-
-            while !monkeys[monkey_idx].items.is_empty() {
-                if let Some(item) = monkeys[monkey_idx].items.pop() {
-                    monkeys[monkey_idx].items_inspected += 1;
-
-                    let old_worry_level = item;
-
-                    let worry_level = match &monkeys[monkey_idx].operation {
-                        Operation::Add(term) => match term {
-                            Term::Old => old_worry_level + old_worry_level,
-                            Term::Value(val) => old_worry_level + val,
-                        },
-                        Operation::Multiply(term) => match term {
-                            Term::Old => old_worry_level * old_worry_level,
-                            Term::Value(val) => old_worry_level * val,
-                        },
-                    };
-
-                    // bored worry level is the worry level divided by 3 and rounded down
-                    let bored_worry_level = worry_level / 3;
-
-                    let receiver_idx = if bored_worry_level % monkeys[monkey_idx].divisor == 0 {
-                        monkeys[monkey_idx].throw_to.0
-                    } else {
-                        monkeys[monkey_idx].throw_to.1
-                    };
-
-                    monkeys[receiver_idx].items.push(bored_worry_level);
-
-                    println!(
-                        "Monkey: {}, Item: {}, Worry: {}, Bored: {}, Receiver: {}",
-                        monkey_idx, item, worry_level, bored_worry_level, receiver_idx
-                    )
-                }
-            }
-        }
+        report.rounds.push(play_round(&mut monkeys, round + 1)?);
 
         println!("------------ Monkeys state -------------");
         (0..monkeys.len()).for_each(|monkey_idx| {
             println!(
                 "Monkey: {}, Items: {:?}, Items Inspected: {}",
-                monkey_idx, monkeys[monkey_idx].items, monkeys[monkey_idx].items_inspected
+                monkey_idx,
+                monkeys[monkey_idx]
+                    .items
+                    .iter()
+                    .map(|item| &item.worry)
+                    .collect::<Vec<_>>(),
+                monkeys[monkey_idx].items_inspected
             )
         });
     }
@@ -195,11 +416,12 @@ fn main() {
         )
     });
 
-    // sort monkeys with items_inspected and get the top two
-    monkeys.sort_by(|a, b| b.items_inspected.cmp(&a.items_inspected));
+    // sort monkeys with items_inspected and get the top ones
+    let mut ranked_monkeys: Vec<&Monkey<W>> = monkeys.iter().collect();
+    ranked_monkeys.sort_by_key(|monkey| std::cmp::Reverse(monkey.items_inspected));
 
-    // get top two monkeys
-    let top_monkeys = &monkeys[0..2];
+    // get top monkeys
+    let top_monkeys = &ranked_monkeys[0..top.min(monkeys.len())];
 
     println!();
     println!("------------ Top Monkeys -------------");
@@ -212,12 +434,142 @@ fn main() {
         )
     });
 
-    // Monkey business is the multiplication of the top two monkeys' items_inspected
-    let monkey_business = top_monkeys
-        .iter()
-        .fold(1, |acc, monkey| acc * monkey.items_inspected);
-
+    // Monkey business is the multiplication of the top monkeys' items_inspected
     println!();
     println!("------------ Monkey Business -------------");
-    println!("Monkey Business: {}", monkey_business);
+    print_monkey_business(report.monkey_business(top));
+
+    Ok((monkeys, report))
+}
+
+fn print_monkey_business(monkey_business: Option<u128>) {
+    match monkey_business {
+        Some(monkey_business) => println!("Monkey Business: {}", monkey_business),
+        None => println!("Monkey Business: too large to fit in 128 bits"),
+    }
+}
+
+// Print the path of every item and the throw graph in Graphviz DOT format
+fn print_item_flow<W: Worry>(monkeys: &[Monkey<W>]) {
+    let mut items: Vec<&Item<W>> = monkeys.iter().flat_map(|m| m.items.iter()).collect();
+    items.sort_by_key(|item| item.id);
+
+    println!();
+    println!("------------ Items -------------");
+    for item in items {
+        println!(
+            "Item: {}, Worry: {}, Held by: {:?}",
+            item.id, item.worry, item.held_by
+        );
+    }
+
+    println!();
+    println!("------------ Throw Graph -------------");
+    print!("{}", graph::to_dot(&graph::throw_graph(monkeys)));
+}
+
+// What to print besides the puzzle output, from the command line arguments
+struct Options {
+    // `--bigint` keeps worry levels as big integers, requires the bigint feature
+    bigint: bool,
+    // `--flow` prints where every item went
+    flow: bool,
+    // `--csv` prints the statistics of every round as CSV
+    csv: bool,
+    // `--top <k>` multiplies the inspections of the k busiest monkeys, 2 by default
+    top: usize,
+}
+
+impl Options {
+    fn from_args() -> Self {
+        let args: Vec<String> = env::args().collect();
+        let top = args
+            .iter()
+            .position(|arg| arg == "--top")
+            .and_then(|idx| args.get(idx + 1))
+            .map(|top| top.parse().expect("--top expects a number"))
+            .unwrap_or(2);
+
+        Self {
+            bigint: args.iter().any(|arg| arg == "--bigint"),
+            flow: args.iter().any(|arg| arg == "--flow"),
+            csv: args.iter().any(|arg| arg == "--csv"),
+            top,
+        }
+    }
+}
+
+fn run<W: Worry>(monkeys: Vec<Monkey<W>>, options: &Options) -> Result<(), WorryOverflow> {
+    let (monkeys, report) = simulate(monkeys, options.top)?;
+
+    println!();
+    println!("------------ Max Worry Levels -------------");
+    for (monkey_idx, max_worry) in report.max_worry().iter().enumerate() {
+        match max_worry {
+            Some(max_worry) => println!("Monkey: {}, Max Worry: {}", monkey_idx, max_worry),
+            None => println!("Monkey: {}, Max Worry: none", monkey_idx),
+        }
+    }
+
+    if options.flow {
+        print_item_flow(&monkeys);
+    }
+    if options.csv {
+        println!();
+        println!("------------ Statistics -------------");
+        print!("{}", report.to_csv());
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let contents = fs::read_to_string("input.txt").expect("Something went wrong reading the file");
+
+    let monkeys =
+        parse_monkeys(&contents).unwrap_or_else(|err| panic!("Error parsing file: {}", err));
+
+    let options = Options::from_args();
+
+    let result = if options.bigint {
+        #[cfg(feature = "bigint")]
+        {
+            run(
+                monkeys
+                    .iter()
+                    .cloned()
+                    .map(Monkey::with_worry::<num_bigint::BigUint>)
+                    .collect(),
+                &options,
+            )
+        }
+        #[cfg(not(feature = "bigint"))]
+        {
+            panic!("Big integer mode requires the bigint feature");
+        }
+    } else {
+        run(monkeys.clone(), &options)
+    };
+
+    if let Err(err) = result {
+        println!("{}", err);
+    }
+
+    // Without relief worry levels are only kept in check by the divisors,
+    // which makes every item cycle and lets us skip ahead to any round
+    for rounds in [10_000, 1_000_000_000_000] {
+        match cycles::extrapolate_inspections(&monkeys, rounds) {
+            Ok(inspections) => {
+                let monkey_business = report::monkey_business(&inspections, options.top);
+
+                println!();
+                println!(
+                    "------------ {} Rounds Without Relief -------------",
+                    rounds
+                );
+                print_monkey_business(monkey_business);
+            }
+            Err(err) => println!("{}", err),
+        }
+    }
 }
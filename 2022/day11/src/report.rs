@@ -0,0 +1,115 @@
+use crate::worry::Worry;
+
+// What happened to each monkey during a single round, indexed by monkey
+#[derive(Debug, Clone)]
+pub struct RoundStats<W> {
+    pub round: usize,
+    pub inspections: Vec<u64>,
+    // The highest worry level a monkey computed, before relief, None if it had no items
+    pub max_worry: Vec<Option<W>>,
+    // The number of items each monkey holds at the end of the round
+    pub items_held: Vec<usize>,
+}
+
+impl<W: Worry> RoundStats<W> {
+    pub fn new(round: usize, monkeys: usize) -> Self {
+        Self {
+            round,
+            inspections: vec![0; monkeys],
+            max_worry: vec![None; monkeys],
+            items_held: vec![0; monkeys],
+        }
+    }
+
+    pub fn record_inspection(&mut self, monkey: usize, worry: &W) {
+        self.inspections[monkey] += 1;
+        if self.max_worry[monkey]
+            .as_ref()
+            .is_none_or(|max| worry > max)
+        {
+            self.max_worry[monkey] = Some(worry.clone());
+        }
+    }
+}
+
+// Statistics for a whole game, one entry per round played
+#[derive(Debug, Clone)]
+pub struct Report<W> {
+    pub rounds: Vec<RoundStats<W>>,
+}
+
+impl<W: Worry> Report<W> {
+    pub fn new() -> Self {
+        Self { rounds: vec![] }
+    }
+
+    pub fn total_inspections(&self) -> Vec<u64> {
+        let mut totals: Vec<u64> = vec![];
+        for round in self.rounds.iter() {
+            totals.resize(round.inspections.len(), 0);
+            for (total, count) in totals.iter_mut().zip(round.inspections.iter()) {
+                *total += count;
+            }
+        }
+        totals
+    }
+
+    // The highest worry level each monkey computed over the whole game
+    pub fn max_worry(&self) -> Vec<Option<W>> {
+        let mut max_worry: Vec<Option<W>> = vec![];
+        for round in self.rounds.iter() {
+            max_worry.resize(round.max_worry.len(), None);
+            for (max, worry) in max_worry.iter_mut().zip(round.max_worry.iter()) {
+                if let Some(worry) = worry {
+                    if max.as_ref().is_none_or(|max| worry > max) {
+                        *max = Some(worry.clone());
+                    }
+                }
+            }
+        }
+        max_worry
+    }
+
+    pub fn monkey_business(&self, top: usize) -> Option<u128> {
+        monkey_business(&self.total_inspections(), top)
+    }
+
+    // One line per monkey per round, with the running total of inspections
+    pub fn to_csv(&self) -> String {
+        let mut csv =
+            String::from("round,monkey,inspections,total_inspections,max_worry,items_held\n");
+        let mut totals: Vec<u64> = vec![];
+
+        for round in self.rounds.iter() {
+            totals.resize(round.inspections.len(), 0);
+            for (monkey, total) in totals.iter_mut().enumerate() {
+                *total += round.inspections[monkey];
+                csv.push_str(&format!(
+                    "{},{},{},{},{},{}\n",
+                    round.round,
+                    monkey,
+                    round.inspections[monkey],
+                    total,
+                    round.max_worry[monkey]
+                        .as_ref()
+                        .map(|worry| worry.to_string())
+                        .unwrap_or_default(),
+                    round.items_held[monkey]
+                ));
+            }
+        }
+
+        csv
+    }
+}
+
+// The product of the inspection counts of the `top` busiest monkeys,
+// None if it doesn't fit in a u128
+pub fn monkey_business(inspections: &[u64], top: usize) -> Option<u128> {
+    let mut inspections = inspections.to_vec();
+    inspections.sort_by_key(|count| std::cmp::Reverse(*count));
+    inspections
+        .iter()
+        .take(top)
+        .try_fold(1u128, |product, count| product.checked_mul(*count as u128))
+}
@@ -0,0 +1,106 @@
+use std::fmt;
+
+use crate::Monkey;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Problem {
+    // The monkey's number doesn't match its position in the notes
+    IndexMismatch { expected: usize, found: u64 },
+    ZeroDivisor,
+    // The monkey throws to a monkey that doesn't exist
+    DanglingTarget { target: usize },
+    // The monkey throws to itself and would never finish its turn
+    SelfThrow,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ValidationError {
+    pub monkey: usize,
+    pub line: usize,
+    pub problem: Problem,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Line {}, monkey {}: ", self.line, self.monkey)?;
+        match &self.problem {
+            Problem::IndexMismatch { expected, found } => {
+                write!(f, "numbered {} but is monkey {}", found, expected)
+            }
+            Problem::ZeroDivisor => write!(f, "tests divisibility by zero"),
+            Problem::DanglingTarget { target } => {
+                write!(f, "throws to monkey {} which doesn't exist", target)
+            }
+            Problem::SelfThrow => write!(f, "throws to itself"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+    // The notes don't follow the expected format from this line on
+    Syntax { line: usize, text: String },
+    Invalid(Vec<ValidationError>),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Syntax { line, text } => {
+                write!(f, "Line {}: unexpected input {:?}", line, text)
+            }
+            ParseError::Invalid(errors) => {
+                write!(f, "{} problem(s) in the notes", errors.len())?;
+                for error in errors.iter() {
+                    write!(f, "\n{}", error)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// Check the monkeys make sense together, `lines` holds the line each monkey starts on
+pub fn validate(monkeys: &[Monkey], lines: &[usize]) -> Result<(), ParseError> {
+    let mut errors = vec![];
+
+    for (monkey_idx, (monkey, &line)) in monkeys.iter().zip(lines.iter()).enumerate() {
+        let mut report = |line: usize, problem: Problem| {
+            errors.push(ValidationError {
+                monkey: monkey_idx,
+                line,
+                problem,
+            })
+        };
+
+        if monkey.index != monkey_idx as u64 {
+            report(
+                line,
+                Problem::IndexMismatch {
+                    expected: monkey_idx,
+                    found: monkey.index,
+                },
+            );
+        }
+
+        // The test is on the fourth line of a monkey, the throws on the next two
+        if monkey.divisor == 0 {
+            report(line + 3, Problem::ZeroDivisor);
+        }
+
+        let (if_true, if_false) = monkey.throw_to;
+        for (target, line) in [(if_true, line + 4), (if_false, line + 5)] {
+            if target >= monkeys.len() {
+                report(line, Problem::DanglingTarget { target });
+            } else if target == monkey_idx {
+                report(line, Problem::SelfThrow);
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ParseError::Invalid(errors))
+    }
+}
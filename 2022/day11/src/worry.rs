@@ -0,0 +1,84 @@
+use std::fmt::{Debug, Display};
+
+// A worry level, arithmetic returns None when the result can't be represented
+// or when dividing by zero
+pub trait Worry: Clone + Debug + Display + Ord {
+    fn from_u64(value: u64) -> Self;
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    fn checked_sub(&self, other: &Self) -> Option<Self>;
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+    fn checked_div(&self, other: &Self) -> Option<Self>;
+    fn checked_rem(&self, other: &Self) -> Option<Self>;
+    fn div_u64(&self, divisor: u64) -> Self;
+    fn is_divisible_by(&self, divisor: u64) -> bool;
+}
+
+impl Worry for u64 {
+    fn from_u64(value: u64) -> Self {
+        value
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        u64::checked_add(*self, *other)
+    }
+
+    fn checked_sub(&self, other: &Self) -> Option<Self> {
+        u64::checked_sub(*self, *other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        u64::checked_mul(*self, *other)
+    }
+
+    fn checked_div(&self, other: &Self) -> Option<Self> {
+        u64::checked_div(*self, *other)
+    }
+
+    fn checked_rem(&self, other: &Self) -> Option<Self> {
+        u64::checked_rem(*self, *other)
+    }
+
+    fn div_u64(&self, divisor: u64) -> Self {
+        self / divisor
+    }
+
+    fn is_divisible_by(&self, divisor: u64) -> bool {
+        self.is_multiple_of(divisor)
+    }
+}
+
+// Big integers never overflow, useful to look at worry levels that are never reduced
+#[cfg(feature = "bigint")]
+impl Worry for num_bigint::BigUint {
+    fn from_u64(value: u64) -> Self {
+        value.into()
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(self + other)
+    }
+
+    fn checked_sub(&self, other: &Self) -> Option<Self> {
+        (self >= other).then(|| self - other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        Some(self * other)
+    }
+
+    fn checked_div(&self, other: &Self) -> Option<Self> {
+        (*other != Self::default()).then(|| self / other)
+    }
+
+    fn checked_rem(&self, other: &Self) -> Option<Self> {
+        (*other != Self::default()).then(|| self % other)
+    }
+
+    fn div_u64(&self, divisor: u64) -> Self {
+        self / divisor
+    }
+
+    fn is_divisible_by(&self, divisor: u64) -> bool {
+        (self % divisor) == num_bigint::BigUint::from(0u64)
+    }
+}
@@ -0,0 +1,47 @@
+use crate::interval::{Interval, IntervalSet};
+
+// How the sections between the lowest and highest assigned section are shared out
+#[derive(Debug, Default)]
+pub struct Coverage {
+    // Sections nobody is assigned to
+    pub uncovered: IntervalSet,
+    // Sections assigned to more than one elf
+    pub multiply_covered: IntervalSet,
+    // Every assignment of a section beyond the first
+    pub redundant_work: u64,
+}
+
+// Sweep over the start and end of every range, so the work depends on the
+// number of ranges and not on how many sections they span
+pub fn analyse(ranges: impl IntoIterator<Item = Interval>) -> Coverage {
+    // +1 elf from the first section of a range, -1 from the section after it
+    let mut events: Vec<(i64, i64)> = ranges
+        .into_iter()
+        .filter(|range| !range.is_empty())
+        .flat_map(|range| [(range.start as i64, 1), (range.end as i64 + 1, -1)])
+        .collect();
+    events.sort_unstable();
+
+    let mut coverage = Coverage::default();
+    let mut elves = 0;
+
+    for (index, &(position, change)) in events.iter().enumerate() {
+        elves += change;
+
+        // The number of elves stays the same until the next event
+        let next_position = match events.get(index + 1) {
+            Some(&(next_position, _)) if next_position > position => next_position,
+            _ => continue,
+        };
+        let segment = Interval::new(position as i32, (next_position - 1) as i32);
+
+        if elves == 0 {
+            coverage.uncovered.insert(segment);
+        } else if elves > 1 {
+            coverage.multiply_covered.insert(segment);
+            coverage.redundant_work += (elves - 1) as u64 * segment.len();
+        }
+    }
+
+    coverage
+}
@@ -0,0 +1,112 @@
+// A range of section IDs, both ends included, empty when the start is after the end
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Interval {
+    pub start: i32,
+    pub end: i32,
+}
+
+impl Interval {
+    pub fn new(start: i32, end: i32) -> Self {
+        Interval { start, end }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start > self.end
+    }
+
+    // Number of sections in the interval
+    pub fn len(&self) -> u64 {
+        (self.end as i64 - self.start as i64 + 1).max(0) as u64
+    }
+
+    // Whether every section of `other` is in this interval
+    pub fn contains(&self, other: &Interval) -> bool {
+        other.is_empty() || (self.start <= other.start && other.end <= self.end)
+    }
+
+    // Whether the intervals share at least one section
+    pub fn overlaps(&self, other: &Interval) -> bool {
+        !self.is_empty() && !other.is_empty() && self.start <= other.end && other.start <= self.end
+    }
+
+    pub fn intersection(&self, other: &Interval) -> Option<Interval> {
+        if self.overlaps(other) {
+            Some(Interval::new(
+                self.start.max(other.start),
+                self.end.min(other.end),
+            ))
+        } else {
+            None
+        }
+    }
+
+    // The interval covering both, if they overlap or touch
+    pub fn union(&self, other: &Interval) -> Option<Interval> {
+        if self.is_empty() {
+            Some(*other)
+        } else if other.is_empty() {
+            Some(*self)
+        } else if self.start as i64 <= other.end as i64 + 1
+            && other.start as i64 <= self.end as i64 + 1
+        {
+            Some(Interval::new(
+                self.start.min(other.start),
+                self.end.max(other.end),
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+// A set of sections stored as sorted intervals, overlapping and touching intervals are merged
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IntervalSet {
+    intervals: Vec<Interval>,
+}
+
+impl IntervalSet {
+    pub fn new() -> Self {
+        IntervalSet::default()
+    }
+
+    pub fn insert(&mut self, interval: Interval) {
+        if interval.is_empty() {
+            return;
+        }
+
+        // The intervals from `first` up to `last` overlap or touch the new one
+        let first = self
+            .intervals
+            .partition_point(|existing| (existing.end as i64) + 1 < interval.start as i64);
+        let last = self
+            .intervals
+            .partition_point(|existing| existing.start as i64 <= interval.end as i64 + 1);
+
+        let merged = self.intervals[first..last]
+            .iter()
+            .fold(interval, |merged, existing| {
+                merged.union(existing).unwrap_or(merged)
+            });
+        self.intervals.splice(first..last, [merged]);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Interval> {
+        self.intervals.iter()
+    }
+
+    // Number of sections in the set
+    pub fn len(&self) -> u64 {
+        self.intervals.iter().map(Interval::len).sum()
+    }
+}
+
+impl FromIterator<Interval> for IntervalSet {
+    fn from_iter<T: IntoIterator<Item = Interval>>(iter: T) -> Self {
+        let mut set = IntervalSet::new();
+        for interval in iter {
+            set.insert(interval);
+        }
+        set
+    }
+}
@@ -1,69 +1,209 @@
-use std::fs;
+mod coverage;
+mod interval;
+
+use std::{fmt, fs};
+
+use nom::{
+    bytes::complete::tag, character::complete, combinator::all_consuming, multi::separated_list1,
+    sequence::separated_pair, IResult,
+};
+
+use interval::{Interval, IntervalSet};
 
 fn main() {
     let input = fs::read_to_string("./input.txt").expect("Failed to read input file");
 
-    let range_pairs = parse_input(&input);
+    let groups = parse_input(&input).unwrap_or_else(|err| panic!("{}", err));
 
-    let fully_contain_count = range_pairs
+    let fully_contain_count = groups
         .iter()
-        .filter(|range_pair| range_pair.fully_overlap())
+        .filter(|group| group.any_contains_all())
         .count();
 
     println!("{} Fully contain", fully_contain_count);
 
-    let partially_contain_count = range_pairs
+    let partially_contain_count = groups
         .iter()
-        .filter(|range_pair| range_pair.partially_contain())
+        .filter(|group| !group.overlapping_pairs().is_empty())
         .count();
 
     println!("{} Partially contain", partially_contain_count);
-}
 
-#[derive(Debug)]
-struct ElvesJobPair {
-    first_elf: (i32, i32),
-    second_elf: (i32, i32),
+    let overlapping_pairs_count: usize = groups
+        .iter()
+        .map(|group| group.overlapping_pairs().len())
+        .sum();
+
+    println!("{} Overlapping pairs of elves", overlapping_pairs_count);
+
+    let shared_sections: u64 = groups
+        .iter()
+        .filter_map(|group| group.common_intersection())
+        .map(|shared| shared.len())
+        .sum();
+
+    println!("{} Sections shared within groups", shared_sections);
+
+    let assigned_sections: IntervalSet = groups
+        .iter()
+        .flat_map(|group| group.elves.iter().copied())
+        .collect();
+
+    println!(
+        "{} Sections assigned in {} ranges",
+        assigned_sections.len(),
+        assigned_sections.iter().count()
+    );
+
+    let coverage = coverage::analyse(groups.iter().flat_map(|group| group.elves.iter().copied()));
+
+    println!(
+        "{} Sections assigned to nobody in {} ranges",
+        coverage.uncovered.len(),
+        coverage.uncovered.iter().count()
+    );
+    println!(
+        "{} Sections assigned to several elves in {} ranges",
+        coverage.multiply_covered.len(),
+        coverage.multiply_covered.iter().count()
+    );
+    println!("{} Sections of redundant work", coverage.redundant_work);
 }
 
-impl ElvesJobPair {
-    fn fully_overlap(&self) -> bool {
-        let first_elf = self.first_elf;
-        let second_elf = self.second_elf;
+// The section ranges assigned to a group of elves, one range per elf
+#[derive(Debug, PartialEq, Eq)]
+struct ElvesJobGroup {
+    elves: Vec<Interval>,
+}
 
-        let first_elf_range = first_elf.0..=first_elf.1;
-        let second_elf_range = second_elf.0..=second_elf.1;
+impl ElvesJobGroup {
+    // Whether one elf's range contains the ranges of every other elf
+    fn any_contains_all(&self) -> bool {
+        self.elves
+            .iter()
+            .any(|elf| self.elves.iter().all(|other| elf.contains(other)))
+    }
 
-        (first_elf_range.contains(&second_elf.0) && first_elf_range.contains(&second_elf.1))
-            || (second_elf_range.contains(&first_elf.0) && second_elf_range.contains(&first_elf.1))
+    // The indices of every pair of elves whose ranges overlap
+    fn overlapping_pairs(&self) -> Vec<(usize, usize)> {
+        let mut pairs = vec![];
+        for (i, first_elf) in self.elves.iter().enumerate() {
+            for (j, second_elf) in self.elves.iter().enumerate().skip(i + 1) {
+                if first_elf.overlaps(second_elf) {
+                    pairs.push((i, j));
+                }
+            }
+        }
+        pairs
     }
 
-    fn partially_contain(&self) -> bool {
-        let first_elf = self.first_elf;
-        let second_elf = self.second_elf;
+    // The sections every elf of the group is assigned to
+    fn common_intersection(&self) -> Option<Interval> {
+        let (first_elf, others) = self.elves.split_first()?;
+        others
+            .iter()
+            .try_fold(*first_elf, |common, elf| common.intersection(elf))
+    }
+}
 
-        let first_elf_range = first_elf.0..=first_elf.1;
-        let second_elf_range = second_elf.0..=second_elf.1;
+#[derive(Debug)]
+struct ParseError {
+    line: usize,
+    text: String,
+}
 
-        (first_elf_range.contains(&second_elf.0) || first_elf_range.contains(&second_elf.1))
-            || (second_elf_range.contains(&first_elf.0) || second_elf_range.contains(&first_elf.1))
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Line {}: invalid assignment {:?}", self.line, self.text)
     }
 }
 
-fn parse_input(input: &str) -> Vec<ElvesJobPair> {
+// A range of sections such as `2-4`
+fn interval_parser(input: &str) -> IResult<&str, Interval> {
+    let (input, (start, end)) = separated_pair(complete::i32, tag("-"), complete::i32)(input)?;
+    Ok((input, Interval::new(start, end)))
+}
+
+// The ranges of a group separated by commas, such as `2-4,6-8`
+fn group_parser(input: &str) -> IResult<&str, ElvesJobGroup> {
+    let (input, elves) = separated_list1(tag(","), interval_parser)(input)?;
+    Ok((input, ElvesJobGroup { elves }))
+}
+
+// One group per line, blank lines are skipped.
+// Groups can have any number of elves, so each one still needs a vector for its ranges.
+fn parse_input(input: &str) -> Result<Vec<ElvesJobGroup>, ParseError> {
     input
         .lines()
-        .map(|line| {
-            let pair: Vec<&str> = line.split(',').collect();
-            let first_elf_jobs_range: Vec<i32> =
-                pair[0].split('-').map(|n| n.parse().unwrap()).collect();
-            let second_elf_job_range: Vec<i32> =
-                pair[1].split('-').map(|n| n.parse().unwrap()).collect();
-
-            ElvesJobPair {
-                first_elf: (first_elf_jobs_range[0], first_elf_jobs_range[1]),
-                second_elf: (second_elf_job_range[0], second_elf_job_range[1]),
-            }
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(index, line)| {
+            all_consuming(group_parser)(line)
+                .map(|(_, group)| group)
+                .map_err(|_| ParseError {
+                    line: index + 1,
+                    text: line.to_string(),
+                })
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{hint::black_box, time::Instant};
+
+    use super::*;
+
+    // The parser this one replaced, splitting every line into intermediate vectors
+    fn parse_input_split(input: &str) -> Vec<ElvesJobGroup> {
+        input
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let elves = line
+                    .split(',')
+                    .map(|range| {
+                        let jobs_range: Vec<i32> =
+                            range.split('-').map(|n| n.parse().unwrap()).collect();
+                        Interval::new(jobs_range[0], jobs_range[1])
+                    })
+                    .collect();
+
+                ElvesJobGroup { elves }
+            })
+            .collect()
+    }
+
+    fn input() -> String {
+        fs::read_to_string("./input.txt").expect("Failed to read input file")
+    }
+
+    #[test]
+    fn parsers_agree() {
+        let input = input();
+        assert_eq!(parse_input(&input).unwrap(), parse_input_split(&input));
+    }
+
+    // `cargo test --release -- --ignored --nocapture` times both parsers on the input
+    #[test]
+    #[ignore]
+    fn benchmark_parsers() {
+        const ITERATIONS: u32 = 1000;
+        let input = input();
+
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            black_box(parse_input(black_box(&input)).unwrap());
+        }
+        let nom_time = start.elapsed() / ITERATIONS;
+
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            black_box(parse_input_split(black_box(&input)));
+        }
+        let split_time = start.elapsed() / ITERATIONS;
+
+        println!("nom parser: {:?} per parse", nom_time);
+        println!("split parser: {:?} per parse", split_time);
+    }
+}
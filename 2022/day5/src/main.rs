@@ -1,85 +1,377 @@
+mod solver;
+
+use std::{env, fmt, ops::Range};
+
 use nom::{
-    branch::alt,
     bytes::complete::tag,
-    character::complete::{self, alpha1, digit1, multispace1, newline, space1},
-    multi::{many1, separated_list1},
-    sequence::{delimited, preceded},
+    character::complete::{self, alphanumeric1, line_ending, multispace1, not_line_ending, space0},
+    combinator::{all_consuming, consumed, opt, verify},
+    multi::{many0, separated_list1},
+    sequence::{delimited, preceded, terminated},
     *,
 };
 
-#[derive(Debug)]
-struct Cargo<'a> {
-    stacks: Vec<Stack<'a>>,
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Cargo {
+    stacks: Vec<Stack>,
     moves: Vec<Move>,
 }
 
-#[derive(Debug, Clone)]
+// Stacks are numbered from 0 here, from 1 in the puzzle input
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct Move {
     from: u32,
     to: u32,
     count: u32,
 }
 
-#[derive(Debug)]
-struct Stack<'a> {
-    crates: Vec<&'a str>,
+// The labels of the crates, from the bottom of the stack to the top
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+struct Stack {
+    crates: Vec<String>,
 }
 
-impl Cargo<'_> {
-    fn move_crates(&mut self) {
-        for Move { count, from, to } in self.moves.iter() {
-            let len = self.stacks[*from as usize].crates.len();
-            let drained = self.stacks[*from as usize]
-                .crates
-                .drain((len - *count as usize)..)
-                .collect::<Vec<&str>>();
-            for c in drained.iter() {
-                self.stacks[*to as usize].crates.push(c);
-            }
+impl Stack {
+    // Build a stack from its labels, bottom crate first
+    fn from_labels<S: std::convert::Into<String>>(labels: impl IntoIterator<Item = S>) -> Self {
+        Stack {
+            crates: labels.into_iter().map(S::into).collect(),
         }
     }
+
+    fn top(&self) -> Option<&str> {
+        self.crates.last().map(String::as_str)
+    }
 }
 
-fn parse_crate(input: &str) -> IResult<&str, Option<&str>> {
-    let (input, c) = alt((
-        tag("   "),
-        delimited(complete::char('['), alpha1, complete::char(']')),
-    ))(input)?;
+impl Cargo {
+    fn new(stacks: Vec<Stack>, moves: Vec<Move>) -> Self {
+        Cargo { stacks, moves }
+    }
+}
 
-    let result = match c {
-        "   " => None,
-        value => Some(value),
-    };
-    Ok((input, result))
+// How the crane carries several crates at once
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Crane {
+    // One crate at a time, so the moved crates end up in reverse order
+    CrateMover9000,
+    // All the crates at once, keeping their order
+    CrateMover9001,
+}
+
+impl Crane {
+    // Move the crates without checking the move, see `Cargo::validate_move`
+    fn apply(&self, stacks: &mut [Stack], m: &Move) {
+        let len = stacks[m.from as usize].crates.len();
+        let mut lifted = stacks[m.from as usize]
+            .crates
+            .drain((len - m.count as usize)..)
+            .collect::<Vec<String>>();
+        if *self == Crane::CrateMover9000 {
+            lifted.reverse();
+        }
+        stacks[m.to as usize].crates.extend(lifted);
+    }
 }
 
-fn line(input: &str) -> IResult<&str, Vec<Option<&str>>> {
-    let (input, result) = separated_list1(tag(" "), parse_crate)(input)?;
+// Which moves to print the stacks after
+#[derive(Debug, Clone)]
+enum ShowSteps {
+    None,
+    All,
+    // Move numbers starting at 1
+    Only(Vec<usize>),
+}
 
-    Ok((input, result))
+impl ShowSteps {
+    fn includes(&self, step: usize) -> bool {
+        match self {
+            ShowSteps::None => false,
+            ShowSteps::All => true,
+            ShowSteps::Only(steps) => steps.contains(&step),
+        }
+    }
 }
 
-fn parse_cargo(input: &str) -> IResult<&str, Cargo> {
-    let (input, crates_horizontal) = separated_list1(newline, line)(input)?;
-    let (input, _) = newline(input)?;
-    let (input, _numbers) = many1(preceded(space1, digit1))(input)?;
-    let (input, _) = multispace1(input)?;
-    let (input, moves) = separated_list1(newline, parse_move)(input)?;
+// A move that can't be carried out with the current stacks
+#[derive(Debug, PartialEq, Eq)]
+enum MoveError {
+    NoSuchStack {
+        step: usize,
+        stack: u32,
+        stacks: usize,
+    },
+    NotEnoughCrates {
+        step: usize,
+        stack: u32,
+        height: usize,
+        count: u32,
+    },
+    // The cranes can't move crates onto the stack they came from
+    SameStack {
+        step: usize,
+        stack: u32,
+    },
+}
 
-    let mut stacks: Vec<Stack> = vec![];
-    for _ in 0..crates_horizontal[0].len() {
-        stacks.push(Stack { crates: vec![] });
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveError::NoSuchStack {
+                step,
+                stack,
+                stacks,
+            } => write!(
+                f,
+                "Move {}: there is no stack {}, only {} stacks",
+                step,
+                stack + 1,
+                stacks
+            ),
+            MoveError::NotEnoughCrates {
+                step,
+                stack,
+                height,
+                count,
+            } => write!(
+                f,
+                "Move {}: can't take {} crates from stack {} which holds {}",
+                step,
+                count,
+                stack + 1,
+                height
+            ),
+            MoveError::SameStack { step, stack } => write!(
+                f,
+                "Move {}: can't move crates from stack {} onto itself",
+                step,
+                stack + 1
+            ),
+        }
     }
+}
 
-    for vec in crates_horizontal.iter().rev() {
-        for (i, c) in vec.iter().enumerate() {
-            if let Some(c) = c {
-                stacks[i].crates.push(c);
+impl Cargo {
+    // Check the move can be made before touching the stacks, steps start at 1
+    fn validate_move(&self, step: usize, m: &Move) -> Result<(), MoveError> {
+        for stack in [m.from, m.to] {
+            if stack as usize >= self.stacks.len() {
+                return Err(MoveError::NoSuchStack {
+                    step,
+                    stack,
+                    stacks: self.stacks.len(),
+                });
             }
         }
+
+        if m.from == m.to {
+            return Err(MoveError::SameStack {
+                step,
+                stack: m.from,
+            });
+        }
+
+        let height = self.stacks[m.from as usize].crates.len();
+        if m.count as usize > height {
+            return Err(MoveError::NotEnoughCrates {
+                step,
+                stack: m.from,
+                height,
+                count: m.count,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn move_crates(&mut self, crane: Crane, show: &ShowSteps) -> Result<(), MoveError> {
+        for step in 0..self.moves.len() {
+            self.validate_move(step + 1, &self.moves[step])?;
+            crane.apply(&mut self.stacks, &self.moves[step]);
+
+            if show.includes(step + 1) {
+                println!("After move {}: {}", step + 1, self.moves[step]);
+                println!("{}", render_stacks(&self.stacks));
+                println!();
+            }
+        }
+
+        Ok(())
+    }
+
+    // Take the moves back, last one first, turning the final stacks into the starting ones.
+    // Both cranes undo a move by moving the crates straight back: the CrateMover 9000
+    // reverses the order of the crates a second time.
+    fn undo_moves(&mut self, crane: Crane) -> Result<(), MoveError> {
+        for step in (0..self.moves.len()).rev() {
+            let inverse = self.moves[step].inverse();
+            self.validate_move(step + 1, &inverse)?;
+            crane.apply(&mut self.stacks, &inverse);
+        }
+
+        Ok(())
+    }
+}
+
+// Draw the stacks the way the puzzle input does, with the stack numbers underneath
+fn render_stacks(stacks: &[Stack]) -> String {
+    // Columns are wide enough for the widest label and the highest stack number
+    let label_width = stacks
+        .iter()
+        .flat_map(|stack| stack.crates.iter())
+        .map(|c| c.len())
+        .chain([stacks.len().to_string().len()])
+        .max()
+        .unwrap_or(1);
+    let height = stacks
+        .iter()
+        .map(|stack| stack.crates.len())
+        .max()
+        .unwrap_or(0);
+
+    let mut lines = vec![];
+    for level in (0..height).rev() {
+        let line = stacks
+            .iter()
+            .map(|stack| match stack.crates.get(level) {
+                // Pad outside the brackets so the parser reads the label back unchanged
+                Some(c) => format!("{:^width$}", format!("[{}]", c), width = label_width + 2),
+                None => " ".repeat(label_width + 2),
+            })
+            .collect::<Vec<String>>()
+            .join(" ");
+        lines.push(line);
+    }
+
+    let numbers = (1..=stacks.len())
+        .map(|number| format!(" {:^width$} ", number, width = label_width))
+        .collect::<Vec<String>>()
+        .join(" ");
+    lines.push(numbers);
+
+    lines.join("\n")
+}
+
+impl Move {
+    // The move that puts the crates back where they came from
+    fn inverse(&self) -> Move {
+        Move {
+            from: self.to,
+            to: self.from,
+            count: self.count,
+        }
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "move {} from {} to {}",
+            self.count,
+            self.from + 1,
+            self.to + 1
+        )
+    }
+}
+
+// Serialise back into the puzzle input format
+impl fmt::Display for Cargo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", render_stacks(&self.stacks))?;
+        // The moves follow a blank line, a puzzle without moves is just the drawing
+        if !self.moves.is_empty() {
+            writeln!(f)?;
+        }
+        for m in self.moves.iter() {
+            write!(f, "\n{}", m)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_crate(input: &str) -> IResult<&str, &str> {
+    delimited(complete::char('['), alphanumeric1, complete::char(']'))(input)
+}
+
+// Every item on a line of the drawing, paired with the span it covers.
+// Empty slots are just spaces, so the line can end early when it was trimmed.
+fn spans<'a, O>(
+    line: &'a str,
+    item: impl FnMut(&'a str) -> IResult<&'a str, O>,
+) -> IResult<&'a str, Vec<(Range<usize>, O)>> {
+    let (rest, items) =
+        all_consuming(terminated(many0(preceded(space0, consumed(item))), space0))(line)?;
+
+    let items = items
+        .into_iter()
+        .map(|(text, value)| {
+            let start = text.as_ptr() as usize - line.as_ptr() as usize;
+            (start..start + text.len(), value)
+        })
+        .collect();
+    Ok((rest, items))
+}
+
+fn crate_row(input: &str) -> IResult<&str, Vec<(Range<usize>, &str)>> {
+    let (input, line) = terminated(not_line_ending, line_ending)(input)?;
+    let (_, crates) = spans(line, parse_crate)?;
+
+    Ok((input, crates))
+}
+
+fn number_row(input: &str) -> IResult<&str, Vec<(Range<usize>, u32)>> {
+    let (input, line) = not_line_ending(input)?;
+    let (_, numbers) = spans(line, complete::u32)?;
+    if numbers.is_empty() {
+        return Err(Err::Error(error::Error::new(
+            input,
+            error::ErrorKind::Digit,
+        )));
+    }
+
+    Ok((input, numbers))
+}
+
+// The stack whose number is closest to the middle of the crate
+fn column_of(numbers: &[(Range<usize>, u32)], span: &Range<usize>) -> usize {
+    // Compare doubled midpoints so odd widths don't get rounded
+    let middle = |range: &Range<usize>| (range.start + range.end) as i64;
+    numbers
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, (number, _))| (middle(number) - middle(span)).abs())
+        .map(|(idx, _)| idx)
+        .unwrap_or_default()
+}
+
+// The drawing of the stacks, down to and including the number row
+fn parse_drawing(input: &str) -> IResult<&str, Vec<Stack>> {
+    let (input, crates_horizontal) = many0(crate_row)(input)?;
+    let (input, numbers) = number_row(input)?;
+
+    // The number row decides how many stacks there are and where they are drawn,
+    // each column is filled from the bottom row up
+    let mut columns: Vec<Vec<&str>> = vec![vec![]; numbers.len()];
+    for row in crates_horizontal.iter().rev() {
+        for (span, label) in row.iter() {
+            columns[column_of(&numbers, span)].push(label);
+        }
     }
+    let stacks = columns.into_iter().map(Stack::from_labels).collect();
 
-    let cargo = Cargo { stacks, moves };
+    Ok((input, stacks))
+}
+
+fn parse_cargo(input: &str) -> IResult<&str, Cargo> {
+    let (input, stacks) = parse_drawing(input)?;
+    let (input, moves) = opt(preceded(
+        multispace1,
+        separated_list1(line_ending, parse_move),
+    ))(input)?;
+    let moves = moves.unwrap_or_default();
+
+    let cargo = Cargo::new(stacks, moves);
 
     Ok((input, cargo))
 }
@@ -88,9 +380,10 @@ fn parse_move(input: &str) -> IResult<&str, Move> {
     let (input, _) = tag("move ")(input)?;
     let (input, number) = complete::u32(input)?;
     let (input, _) = tag(" from ")(input)?;
-    let (input, from) = complete::u32(input)?;
+    // Stacks are numbered from 1
+    let (input, from) = verify(complete::u32, |n| *n > 0)(input)?;
     let (input, _) = tag(" to ")(input)?;
-    let (input, to) = complete::u32(input)?;
+    let (input, to) = verify(complete::u32, |n| *n > 0)(input)?;
 
     Ok((
         input,
@@ -107,22 +400,147 @@ fn main() {
     let input = std::fs::read_to_string("input.txt").unwrap();
     let (_, mut cargo) = parse_cargo(input.as_str()).unwrap();
 
-    cargo.move_crates();
+    // `cargo run -- --show all` prints the stacks after every move,
+    // `cargo run -- --show 1,5,10` only after the given moves,
+    // `cargo run -- --serialise` prints the parsed puzzle back,
+    // `cargo run -- --crane 9000` moves one crate at a time and
+    // `cargo run -- --solve target.txt` finds the fewest moves to reach the drawing in the file,
+    // `cargo run -- --undo final.txt` takes the moves back from the drawing in the file
+    let args: Vec<String> = env::args().collect();
+    if args.iter().any(|arg| arg == "--serialise") {
+        println!("{}", cargo);
+        return;
+    }
+    let crane = match args.iter().position(|arg| arg == "--crane") {
+        Some(idx) => match args.get(idx + 1).map(String::as_str) {
+            Some("9000") => Crane::CrateMover9000,
+            Some("9001") => Crane::CrateMover9001,
+            _ => panic!("--crane expects 9000 or 9001"),
+        },
+        None => Crane::CrateMover9001,
+    };
+    if let Some(idx) = args.iter().position(|arg| arg == "--solve") {
+        let path = args.get(idx + 1).expect("--solve expects a file");
+        let drawing = std::fs::read_to_string(path).unwrap();
+        let (_, target) = parse_drawing(drawing.as_str()).unwrap();
+
+        match solver::shortest_moves(&cargo.stacks, &target, crane, 1_000_000) {
+            Ok(moves) => {
+                for m in moves.iter() {
+                    println!("{}", m);
+                }
+                println!("{} moves", moves.len());
+            }
+            Err(err) => println!("{}", err),
+        }
+        return;
+    }
+    if let Some(idx) = args.iter().position(|arg| arg == "--undo") {
+        let path = args.get(idx + 1).expect("--undo expects a file");
+        let drawing = std::fs::read_to_string(path).unwrap();
+        let (_, stacks) = parse_drawing(drawing.as_str()).unwrap();
 
-    // get the top crate of each stack
-    let mut top_crates = vec![];
-    for stack in cargo.stacks.iter() {
-        if let Some(c) = stack.crates.last() {
-            top_crates.push(c);
+        let mut undone = Cargo::new(stacks, cargo.moves.clone());
+        match undone.undo_moves(crane) {
+            Ok(()) => println!("{}", render_stacks(&undone.stacks)),
+            Err(err) => println!("{}", err),
         }
+        return;
+    }
+    let show = match args.iter().position(|arg| arg == "--show") {
+        Some(idx) => match args.get(idx + 1).map(String::as_str) {
+            Some("all") => ShowSteps::All,
+            Some(steps) => ShowSteps::Only(
+                steps
+                    .split(',')
+                    .map(|step| step.parse().expect("--show expects move numbers"))
+                    .collect(),
+            ),
+            None => ShowSteps::All,
+        },
+        None => ShowSteps::None,
+    };
+
+    if !matches!(show, ShowSteps::None) {
+        println!("Starting stacks:");
+        println!("{}", render_stacks(&cargo.stacks));
+        println!();
+    }
+
+    if let Err(err) = cargo.move_crates(crane, &show) {
+        println!("{}", err);
+        return;
     }
 
-    // Collect the top crates into a string
-    let top_crates = top_crates
+    // Collect the top crate of each stack into a string
+    let top_crates = cargo
+        .stacks
         .iter()
-        .map(|c| c.to_string())
-        .collect::<Vec<String>>()
+        .filter_map(Stack::top)
+        .collect::<Vec<&str>>()
         .join("");
 
     println!("{:?}", top_crates);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(cargo: &Cargo) {
+        let serialised = cargo.to_string();
+        let (_, parsed) = parse_cargo(&serialised).unwrap();
+        assert_eq!(&parsed, cargo);
+    }
+
+    #[test]
+    fn serialise_round_trips_the_input() {
+        let input = std::fs::read_to_string("input.txt").unwrap();
+        let (_, cargo) = parse_cargo(&input).unwrap();
+        round_trip(&cargo);
+    }
+
+    #[test]
+    fn serialise_round_trips_labels_of_different_widths() {
+        let cargo = Cargo::new(
+            vec![
+                Stack::from_labels(["C", "AB"]),
+                Stack::from_labels(["D1"]),
+                Stack::from_labels(["E"]),
+                Stack::default(),
+            ],
+            vec![Move {
+                from: 0,
+                to: 3,
+                count: 2,
+            }],
+        );
+        round_trip(&cargo);
+    }
+
+    #[test]
+    fn serialise_round_trips_more_than_nine_stacks() {
+        let stacks = ["A", "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "L"]
+            .iter()
+            .map(|label| Stack::from_labels([*label]))
+            .collect();
+        let cargo = Cargo::new(
+            stacks,
+            vec![Move {
+                from: 11,
+                to: 0,
+                count: 1,
+            }],
+        );
+        round_trip(&cargo);
+    }
+
+    #[test]
+    fn serialise_round_trips_without_moves() {
+        let cargo = Cargo::new(
+            vec![Stack::from_labels(["A", "B"]), Stack::from_labels(["C"])],
+            vec![],
+        );
+        round_trip(&cargo);
+    }
+}
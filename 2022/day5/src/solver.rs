@@ -0,0 +1,122 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+};
+
+use crate::{Crane, Move, Stack};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SolveError {
+    StackCount { start: usize, target: usize },
+    // The target holds crates the start doesn't, or the other way round
+    DifferentCrates,
+    // The search visited `limit` arrangements without finding the target
+    TooManyStates { limit: usize },
+    // No sequence of moves reaches the target, with a single stack nothing can move
+    // and the CrateMover 9000 can't reorder the crates of two stacks
+    Unreachable,
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SolveError::StackCount { start, target } => write!(
+                f,
+                "The start has {} stacks but the target has {}",
+                start, target
+            ),
+            SolveError::DifferentCrates => {
+                write!(f, "The start and the target hold different crates")
+            }
+            SolveError::TooManyStates { limit } => write!(
+                f,
+                "Gave up after looking at {} arrangements of the stacks",
+                limit
+            ),
+            SolveError::Unreachable => write!(f, "No sequence of moves reaches the target"),
+        }
+    }
+}
+
+fn sorted_labels(stacks: &[Stack]) -> Vec<&str> {
+    let mut labels: Vec<&str> = stacks
+        .iter()
+        .flat_map(|stack| stack.crates.iter())
+        .map(String::as_str)
+        .collect();
+    labels.sort_unstable();
+    labels
+}
+
+// Every move that can be made from the given stacks
+fn possible_moves(stacks: &[Stack]) -> impl Iterator<Item = Move> + '_ {
+    let count = stacks.len() as u32;
+    (0..count).flat_map(move |from| {
+        let height = stacks[from as usize].crates.len() as u32;
+        (0..count)
+            .filter(move |&to| to != from)
+            .flat_map(move |to| (1..=height).map(move |count| Move { from, to, count }))
+    })
+}
+
+// Find the shortest sequence of moves that turns the `start` stacks into the `target` ones.
+// The number of arrangements grows very quickly, so the search gives up after looking
+// at `limit` of them.
+pub fn shortest_moves(
+    start: &[Stack],
+    target: &[Stack],
+    crane: Crane,
+    limit: usize,
+) -> Result<Vec<Move>, SolveError> {
+    if start.len() != target.len() {
+        return Err(SolveError::StackCount {
+            start: start.len(),
+            target: target.len(),
+        });
+    }
+    if sorted_labels(start) != sorted_labels(target) {
+        return Err(SolveError::DifferentCrates);
+    }
+
+    // Breadth first over the arrangements, each one remembers the arrangement
+    // it was reached from and the move that got there
+    let mut arrangements: Vec<Vec<Stack>> = vec![start.to_vec()];
+    let mut reached_by: Vec<Option<(usize, Move)>> = vec![None];
+    let mut seen: HashMap<Vec<Stack>, usize> = HashMap::from([(start.to_vec(), 0)]);
+    let mut queue = VecDeque::from([0]);
+
+    let mut found = None;
+    while let Some(current) = queue.pop_front() {
+        if arrangements[current] == target {
+            found = Some(current);
+            break;
+        }
+
+        let moves: Vec<Move> = possible_moves(&arrangements[current]).collect();
+        for m in moves {
+            let mut next = arrangements[current].clone();
+            crane.apply(&mut next, &m);
+            if seen.contains_key(&next) {
+                continue;
+            }
+            if arrangements.len() >= limit {
+                return Err(SolveError::TooManyStates { limit });
+            }
+
+            seen.insert(next.clone(), arrangements.len());
+            queue.push_back(arrangements.len());
+            arrangements.push(next);
+            reached_by.push(Some((current, m)));
+        }
+    }
+
+    let mut current = found.ok_or(SolveError::Unreachable)?;
+    let mut moves = vec![];
+    while let Some((previous, m)) = reached_by[current].take() {
+        moves.push(m);
+        current = previous;
+    }
+    moves.reverse();
+
+    Ok(moves)
+}
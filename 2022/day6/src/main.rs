@@ -1,80 +1,52 @@
-use std::fs::read_to_string;
+mod stream;
 
-struct LimitedQueue {
-    inner: Vec<char>,
-    limit: usize,
-}
+use std::{env, fs::File, io};
 
-impl LimitedQueue {
-    fn new(limit: usize) -> Self {
-        LimitedQueue {
-            inner: Vec::with_capacity(limit),
-            limit,
-        }
-    }
+use stream::scan_markers;
 
-    fn is_full(&self) -> bool {
-        self.inner.len() == self.limit
-    }
-
-    fn enqueue(&mut self, item: char) {
-        if self.is_full() {
-            self.inner.remove(0);
-        }
-        self.inner.push(item);
-    }
-
-    fn all_different(&self) -> bool {
-        let mut chars = self.inner.clone();
-        chars.sort();
-        chars.dedup();
-        chars.len() == self.inner.len()
-    }
-}
+// Looking for packets with 4 different characters and messages with 14
+const MARKER_SIZES: [usize; 2] = [4, 14];
 
 fn main() {
-    // Read the input file
-    let input = read_to_string("input.txt").unwrap();
-    let chars = input.chars();
-
-    // Looking for packet with 4 different characters
-    let mut packet_queue = LimitedQueue::new(4);
-    // Push the first 3 elements into the queue
-    let mut packet_input = chars.clone();
-    for _ in 0..3 {
-        packet_queue.enqueue(packet_input.next().unwrap());
-    }
-    // Iterate over the characters
-    for (index, c) in packet_input.enumerate() {
-        packet_queue.enqueue(c);
-        if packet_queue.all_different() {
-            println!(
-                "Found a match: {:?}, after {} steps",
-                packet_queue.inner,
-                index + 4
-            );
-            break;
+    // `cargo run -- <file>` reads another file and `cargo run -- -` reads stdin,
+    // `cargo run -- --all` prints every marker instead of only the first of each size
+    let args: Vec<String> = env::args().skip(1).collect();
+    let print_all = args.iter().any(|arg| arg == "--all");
+    let path = args
+        .iter()
+        .find(|arg| *arg != "--all")
+        .map(String::as_str)
+        .unwrap_or("input.txt");
+
+    let mut first_found = [false; MARKER_SIZES.len()];
+    let mut counts = [0u64; MARKER_SIZES.len()];
+    let on_marker = |detector: &stream::MarkerDetector, offset: u64| {
+        let idx = MARKER_SIZES
+            .iter()
+            .position(|&size| size == detector.size())
+            .unwrap();
+        counts[idx] += 1;
+
+        if print_all {
+            println!("{}: {}", detector.size(), offset);
+        } else if !first_found[idx] {
+            first_found[idx] = true;
+            let window: Vec<char> = detector.window().into_iter().map(char::from).collect();
+            println!("Found a match: {:?}, after {} steps", window, offset);
         }
-    }
+    };
 
-    // Looking for packet with 14 different characters
-    let mut packet_queue = LimitedQueue::new(14);
-    // Push the first 13 elements into the queue
-    let mut message_input = chars.clone();
-    for _ in 0..13 {
-        packet_queue.enqueue(message_input.next().unwrap());
+    // The input is scanned in chunks so streams of any length fit in memory
+    let read = if path == "-" {
+        scan_markers(io::stdin().lock(), &MARKER_SIZES, on_marker)
+    } else {
+        scan_markers(File::open(path).unwrap(), &MARKER_SIZES, on_marker)
     }
+    .unwrap();
 
-    // Iterate over the characters
-    for (index, c) in message_input.enumerate() {
-        packet_queue.enqueue(c);
-        if packet_queue.all_different() {
-            println!(
-                "Found a match: {:?}, after {} steps",
-                packet_queue.inner,
-                index + 14
-            );
-            break;
+    if print_all {
+        for (size, count) in MARKER_SIZES.iter().zip(counts) {
+            println!("{} markers of {} characters in {} bytes", count, size, read);
         }
     }
 }
@@ -0,0 +1,195 @@
+use std::io::{self, ErrorKind, Read};
+
+// Bytes read from the source at a time
+const CHUNK_SIZE: usize = 64 * 1024;
+
+// The last `size` bytes of the stream, kept track of so checking them takes constant time
+pub struct MarkerDetector {
+    window: Vec<u8>,
+    // Where the next byte goes in `window`, it is also the oldest byte once the window is full
+    next: usize,
+    filled: usize,
+    counts: [u32; 256],
+    // Number of byte values appearing more than once in the window
+    repeated: usize,
+}
+
+impl MarkerDetector {
+    // None for a size of 0, every marker has at least one byte
+    pub fn new(size: usize) -> Option<Self> {
+        if size == 0 {
+            return None;
+        }
+
+        Some(MarkerDetector {
+            window: vec![0; size],
+            next: 0,
+            filled: 0,
+            counts: [0; 256],
+            repeated: 0,
+        })
+    }
+
+    // Add a byte to the window, returns whether the window is now a marker
+    pub fn push(&mut self, byte: u8) -> bool {
+        let size = self.window.len();
+        if self.filled == size {
+            let oldest = self.window[self.next] as usize;
+            self.counts[oldest] -= 1;
+            if self.counts[oldest] == 1 {
+                self.repeated -= 1;
+            }
+        } else {
+            self.filled += 1;
+        }
+
+        self.window[self.next] = byte;
+        self.next = (self.next + 1) % size;
+        self.counts[byte as usize] += 1;
+        if self.counts[byte as usize] == 2 {
+            self.repeated += 1;
+        }
+
+        self.filled == size && self.repeated == 0
+    }
+
+    // Forget the bytes seen so far
+    pub fn reset(&mut self) {
+        self.next = 0;
+        self.filled = 0;
+        self.counts = [0; 256];
+        self.repeated = 0;
+    }
+
+    pub fn size(&self) -> usize {
+        self.window.len()
+    }
+
+    // The bytes in the window, oldest first
+    pub fn window(&self) -> Vec<u8> {
+        if self.filled < self.window.len() {
+            return self.window[..self.filled].to_vec();
+        }
+        let (newer, older) = self.window.split_at(self.next);
+        older.iter().chain(newer).copied().collect()
+    }
+}
+
+// Scan the source in fixed-size chunks for windows of `size` distinct bytes, for each
+// of the given sizes at once. Every marker found is passed to `on_marker` together with
+// the detector and the number of bytes read up to the end of the marker.
+// Each line is a separate signal: `\n` and `\r` are never part of a marker and start
+// the search over, but they still count towards the offsets.
+// Returns the number of bytes read.
+pub fn scan_markers(
+    mut source: impl Read,
+    sizes: &[usize],
+    mut on_marker: impl FnMut(&MarkerDetector, u64),
+) -> io::Result<u64> {
+    let mut detectors: Vec<MarkerDetector> = sizes
+        .iter()
+        .map(|&size| MarkerDetector::new(size))
+        .collect::<Option<_>>()
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "Marker size must be at least 1"))?;
+    let mut chunk = vec![0; CHUNK_SIZE];
+    let mut offset = 0;
+
+    loop {
+        let read = match source.read(&mut chunk) {
+            Ok(0) => return Ok(offset),
+            Ok(read) => read,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+
+        for &byte in chunk[..read].iter() {
+            offset += 1;
+            if byte == b'\n' || byte == b'\r' {
+                detectors.iter_mut().for_each(MarkerDetector::reset);
+                continue;
+            }
+            for detector in detectors.iter_mut() {
+                if detector.push(byte) {
+                    on_marker(detector, offset);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Hands out the bytes 1, 2 or 3 at a time so markers span several reads
+    struct TrickleReader<'a> {
+        bytes: &'a [u8],
+        reads: usize,
+    }
+
+    impl Read for TrickleReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = (self.reads % 3 + 1).min(self.bytes.len()).min(buf.len());
+            self.reads += 1;
+            buf[..len].copy_from_slice(&self.bytes[..len]);
+            self.bytes = &self.bytes[len..];
+            Ok(len)
+        }
+    }
+
+    fn markers(input: &str, sizes: &[usize]) -> Vec<(usize, u64)> {
+        let reader = TrickleReader {
+            bytes: input.as_bytes(),
+            reads: 0,
+        };
+        let mut found = vec![];
+        let read = scan_markers(reader, sizes, |detector, offset| {
+            found.push((detector.size(), offset))
+        })
+        .unwrap();
+        assert_eq!(read, input.len() as u64);
+        found
+    }
+
+    // Check every window of every line directly
+    fn brute_force(input: &str, size: usize) -> Vec<(usize, u64)> {
+        let bytes = input.as_bytes();
+        (size..=bytes.len())
+            .filter(|&end| {
+                let window = &bytes[end - size..end];
+                !window.iter().any(|&b| b == b'\n' || b == b'\r')
+                    && window
+                        .iter()
+                        .enumerate()
+                        .all(|(i, b)| !window[i + 1..].contains(b))
+            })
+            .map(|end| (size, end as u64))
+            .collect()
+    }
+
+    #[test]
+    fn finds_every_marker_across_reads() {
+        let input = "mjqjpqmgbljsphdztnvjfqwrcgsmlb";
+        let found = markers(input, &[4]);
+
+        assert_eq!(found.first(), Some(&(4, 7)));
+        assert_eq!(found, brute_force(input, 4));
+        assert_eq!(markers(input, &[14]).first(), Some(&(14, 19)));
+    }
+
+    #[test]
+    fn line_terminators_start_the_search_over() {
+        assert_eq!(markers("abc\ndabc", &[4]), vec![(4, 8)]);
+        assert_eq!(markers("abc\rd", &[4]), vec![]);
+
+        let input = "abcd\r\nbcde\nxyz\nabcdefghijklmnop";
+        assert_eq!(markers(input, &[4]), brute_force(input, 4));
+        assert_eq!(markers(input, &[14]), brute_force(input, 14));
+    }
+
+    #[test]
+    fn rejects_a_marker_size_of_zero() {
+        let result = scan_markers("abcd".as_bytes(), &[4, 0], |_, _| {});
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+}